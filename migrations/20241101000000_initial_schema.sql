@@ -0,0 +1,48 @@
+CREATE TABLE IF NOT EXISTS users (
+  id SERIAL PRIMARY KEY,
+  username TEXT,
+  first_name TEXT,
+  last_name TEXT,
+  team TEXT,
+  created_at INT
+);
+
+CREATE TABLE IF NOT EXISTS forums (
+  id SERIAL PRIMARY KEY,
+  name TEXT,
+  created_at INT
+);
+
+CREATE TABLE IF NOT EXISTS submissions (
+  message_id SERIAL PRIMARY KEY,
+  user int,
+  team TEXT,
+  date INT,
+  caption TEXT,
+  type INT
+);
+
+CREATE TABLE IF NOT EXISTS challenges (
+  name TEXT PRIMARY KEY,
+  short_name TEXT,
+  desc TEXT,
+  points INT
+);
+
+CREATE TABLE IF NOT EXISTS judgement (
+  submission_id INT PRIMARY KEY,
+  challenge_name TEXT,
+  points INT,
+  valid BOOLEAN
+);
+
+CREATE TABLE IF NOT EXISTS config (
+  name TEXT PRIMARY KEY,
+  value TEXT
+);
+
+CREATE TABLE IF NOT EXISTS safety_team (
+  name TEXT PRIMARY KEY,
+  phone TEXT,
+  date TEXT
+);
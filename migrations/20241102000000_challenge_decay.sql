@@ -0,0 +1,4 @@
+-- Optional exponential decay of the points from `starts_at` on, never below `decay_floor`
+ALTER TABLE challenges ADD COLUMN starts_at TEXT;
+ALTER TABLE challenges ADD COLUMN decay_half_life REAL;
+ALTER TABLE challenges ADD COLUMN decay_floor INT;
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS team_changes (
+  user_id INT,
+  old_team TEXT,
+  new_team TEXT,
+  changed_at TEXT
+);
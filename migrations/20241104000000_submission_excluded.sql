@@ -0,0 +1,2 @@
+-- Submissions made for a team the participant has left, never scored
+ALTER TABLE submissions ADD COLUMN excluded BOOLEAN DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE challenges ADD COLUMN emoji TEXT;
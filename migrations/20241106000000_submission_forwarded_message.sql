@@ -0,0 +1,2 @@
+-- Message id of the forward in the judge chat, judges reply to it
+ALTER TABLE submissions ADD COLUMN forwarded_message_id INT;
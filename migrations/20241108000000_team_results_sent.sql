@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS team_results_sent (
+  team TEXT PRIMARY KEY,
+  sent_at TEXT
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS adjustments (
+  team TEXT,
+  points INT,
+  reason TEXT,
+  created_by INT,
+  created_at TEXT
+);
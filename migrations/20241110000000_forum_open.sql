@@ -0,0 +1,2 @@
+-- Whether the team's forum topic is open
+ALTER TABLE forums ADD COLUMN open BOOLEAN DEFAULT 1;
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS teams (
+  name TEXT PRIMARY KEY,
+  code TEXT UNIQUE,
+  created_at TEXT
+);
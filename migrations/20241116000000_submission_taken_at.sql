@@ -0,0 +1 @@
+ALTER TABLE submissions ADD COLUMN taken_at TEXT;
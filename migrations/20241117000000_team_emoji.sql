@@ -0,0 +1,2 @@
+ALTER TABLE teams ADD COLUMN emoji TEXT;
+ALTER TABLE forums ADD COLUMN title TEXT;
@@ -0,0 +1,2 @@
+-- Message asking the participant for a clear caption, replies to it update the submission
+ALTER TABLE submissions ADD COLUMN clarification_message_id INTEGER;
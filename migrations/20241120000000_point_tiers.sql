@@ -0,0 +1,4 @@
+-- Comma separated points judges choose from, e.g. '1,2,3'
+ALTER TABLE challenges ADD COLUMN tiers TEXT;
+-- Chosen tier, 1 for the first value of challenges.tiers
+ALTER TABLE judgement ADD COLUMN tier INT;
@@ -0,0 +1,2 @@
+-- Maintainer who made the judgement, NULL for judgements made before it was tracked
+ALTER TABLE judgement ADD COLUMN judge_user_id INT;
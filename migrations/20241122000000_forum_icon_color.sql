@@ -0,0 +1 @@
+ALTER TABLE forums ADD COLUMN icon_color INT;
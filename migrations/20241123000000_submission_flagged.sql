@@ -0,0 +1,2 @@
+-- Flagged by a judge for review; stays pending until judged
+ALTER TABLE submissions ADD COLUMN flagged BOOLEAN NOT NULL DEFAULT 0;
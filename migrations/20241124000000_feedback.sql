@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS feedback (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id INT,
+  -- Disputed submission, NULL for general feedback
+  submission_id INT,
+  text TEXT,
+  created_at TEXT
+);
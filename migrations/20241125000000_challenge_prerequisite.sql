@@ -0,0 +1,2 @@
+-- Name of the challenge a team has to complete before this one is offered
+ALTER TABLE challenges ADD COLUMN prerequisite TEXT;
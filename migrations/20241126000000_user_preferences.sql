@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS user_preferences (
+  user_id INT PRIMARY KEY,
+  -- No replies to casual messages
+  muted_chit_chat BOOLEAN NOT NULL DEFAULT 0
+);
@@ -0,0 +1,2 @@
+-- Judge chat the submission was forwarded to, NULL means the first JUDGE_CHAT_ID
+ALTER TABLE submissions ADD COLUMN judge_chat_id INT;
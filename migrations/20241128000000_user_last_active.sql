@@ -0,0 +1,2 @@
+-- Time of the latest message from the user (UTC), NULL if none since tracking started
+ALTER TABLE users ADD COLUMN last_active TEXT;
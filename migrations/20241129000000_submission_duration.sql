@@ -0,0 +1,2 @@
+-- Length of video submissions in seconds, NULL for photos
+ALTER TABLE submissions ADD COLUMN duration INT;
@@ -0,0 +1,2 @@
+-- Blurred placeholder of photo submissions (https://blurha.sh), NULL for videos
+ALTER TABLE submissions ADD COLUMN blurhash TEXT;
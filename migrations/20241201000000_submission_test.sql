@@ -0,0 +1,2 @@
+-- Fake submissions created with /test_submission to try the judging flow, never scored
+ALTER TABLE submissions ADD COLUMN test BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Only the first team to complete the challenge gets its points
+ALTER TABLE challenges ADD COLUMN first_only BOOLEAN NOT NULL DEFAULT 0;
+-- Why a judgement doesn't count, e.g. a first_only challenge another team claimed
+ALTER TABLE judgement ADD COLUMN note TEXT;
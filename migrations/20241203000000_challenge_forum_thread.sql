@@ -0,0 +1,2 @@
+-- Topic of the judge forum that judged submissions of the challenge are copied to
+ALTER TABLE challenges ADD COLUMN forum_thread_id INTEGER;
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS schedule (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  -- UTC
+  starts_at TEXT NOT NULL,
+  title TEXT NOT NULL,
+  -- Participants were reminded, or the event was already past when the bot started
+  reminded BOOLEAN NOT NULL DEFAULT 0
+);
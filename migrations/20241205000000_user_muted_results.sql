@@ -0,0 +1,2 @@
+-- No messages congratulating on judged submissions, the reactions still show the verdict
+ALTER TABLE user_preferences ADD COLUMN muted_results BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Comma separated extra points for the first, second, ... team to complete the challenge
+ALTER TABLE challenges ADD COLUMN podium_bonus TEXT;
+-- Place among the first teams to complete the challenge and the bonus included in points
+ALTER TABLE judgement ADD COLUMN podium_place INTEGER;
+ALTER TABLE judgement ADD COLUMN bonus INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Topics closed with /close_forum, which the reconciliation doesn't reopen
+ALTER TABLE forums ADD COLUMN closed_manually BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,471 @@
+//! Telegram calls of the submission, judging and forum flows
+//!
+//! The flows only talk to Telegram through [`BotActions`], which is implemented by
+//! [`Actions`]: either the real [`Bot`] or a [`DryRunBot`] that records the calls instead
+//! of executing them (enabled with `DRY_RUN=1`).
+use crate::error::BotResult;
+use std::{
+    future::Future,
+    path::Path,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+};
+use teloxide::{
+    net::Download,
+    prelude::*,
+    types::{
+        ChatAction, InlineKeyboardMarkup, MessageId, ParseMode, ReactionType, Recipient,
+        ReplyParameters, ThreadId,
+    },
+    ApiError, RequestError,
+};
+use tokio::fs;
+
+pub type ActionResult<T> = BotResult<T>;
+
+/// Optional parameters of [`BotActions::send_text`]
+#[derive(Clone, Debug, Default)]
+pub struct SendOptions {
+    pub reply_to: Option<MessageId>,
+    pub thread_id: Option<ThreadId>,
+    pub keyboard: Option<InlineKeyboardMarkup>,
+    pub parse_mode: Option<ParseMode>,
+    pub silent: bool,
+}
+
+pub trait BotActions: Send + Sync {
+    /// Send a text message and return its id
+    fn send_text(
+        &self,
+        chat_id: ChatId,
+        text: String,
+        options: SendOptions,
+    ) -> impl Future<Output = ActionResult<MessageId>> + Send;
+
+    /// Forward a message and return the id of the forwarded copy
+    fn forward(
+        &self,
+        to: ChatId,
+        from: ChatId,
+        message_id: MessageId,
+        thread_id: Option<ThreadId>,
+    ) -> impl Future<Output = ActionResult<MessageId>> + Send;
+
+    /// Replace the bot's reaction on a message, `None` removes it
+    fn react(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        emoji: Option<String>,
+    ) -> impl Future<Output = ActionResult<()>> + Send;
+
+    /// Show the chat that the bot is busy, e.g. uploading a photo
+    fn chat_action(
+        &self,
+        chat_id: ChatId,
+        action: ChatAction,
+    ) -> impl Future<Output = ActionResult<()>> + Send;
+
+    /// Download a file sent to the bot to `destination`
+    fn download(
+        &self,
+        file_id: String,
+        destination: &Path,
+    ) -> impl Future<Output = ActionResult<()>> + Send;
+
+    /// Create a forum topic with one of the icon colors Telegram allows
+    fn create_topic(
+        &self,
+        forum: Recipient,
+        name: String,
+        icon_color: u32,
+    ) -> impl Future<Output = ActionResult<ThreadId>> + Send;
+
+    fn close_topic(
+        &self,
+        forum: Recipient,
+        thread_id: ThreadId,
+    ) -> impl Future<Output = ActionResult<()>> + Send;
+
+    fn reopen_topic(
+        &self,
+        forum: Recipient,
+        thread_id: ThreadId,
+    ) -> impl Future<Output = ActionResult<()>> + Send;
+
+    fn rename_topic(
+        &self,
+        forum: Recipient,
+        thread_id: ThreadId,
+        name: String,
+    ) -> impl Future<Output = ActionResult<()>> + Send;
+}
+
+impl BotActions for Bot {
+    async fn send_text(
+        &self,
+        chat_id: ChatId,
+        text: String,
+        options: SendOptions,
+    ) -> ActionResult<MessageId> {
+        let mut request = self
+            .send_message(chat_id, text)
+            .disable_notification(options.silent);
+        request.reply_parameters = options.reply_to.map(ReplyParameters::new);
+        request.message_thread_id = options.thread_id;
+        request.parse_mode = options.parse_mode;
+        request.reply_markup = options.keyboard.map(Into::into);
+        Ok(request.await?.id)
+    }
+
+    async fn forward(
+        &self,
+        to: ChatId,
+        from: ChatId,
+        message_id: MessageId,
+        thread_id: Option<ThreadId>,
+    ) -> ActionResult<MessageId> {
+        let mut request = self.forward_message(to, from, message_id);
+        request.message_thread_id = thread_id;
+        Ok(request.await?.id)
+    }
+
+    async fn react(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        emoji: Option<String>,
+    ) -> ActionResult<()> {
+        let request = self.set_message_reaction(chat_id, message_id);
+        match emoji {
+            Some(emoji) => {
+                request
+                    .reaction(vec![ReactionType::Emoji { emoji }])
+                    .await?
+            }
+            // Without a reaction, the existing ones are cleared
+            None => request.await?,
+        };
+        Ok(())
+    }
+
+    async fn chat_action(&self, chat_id: ChatId, action: ChatAction) -> ActionResult<()> {
+        self.send_chat_action(chat_id, action).await?;
+        Ok(())
+    }
+
+    async fn download(&self, file_id: String, destination: &Path) -> ActionResult<()> {
+        let file = self.get_file(file_id).await?;
+        let mut dst = fs::File::create(destination).await?;
+        self.download_file(&file.path, &mut dst).await?;
+        Ok(())
+    }
+
+    async fn create_topic(
+        &self,
+        forum: Recipient,
+        name: String,
+        icon_color: u32,
+    ) -> ActionResult<ThreadId> {
+        let topic = self
+            .create_forum_topic(forum, name, icon_color, "🔥")
+            .await?;
+        Ok(topic.thread_id)
+    }
+
+    async fn close_topic(&self, forum: Recipient, thread_id: ThreadId) -> ActionResult<()> {
+        self.close_forum_topic(forum, thread_id).await?;
+        Ok(())
+    }
+
+    async fn reopen_topic(&self, forum: Recipient, thread_id: ThreadId) -> ActionResult<()> {
+        self.reopen_forum_topic(forum, thread_id).await?;
+        Ok(())
+    }
+
+    async fn rename_topic(
+        &self,
+        forum: Recipient,
+        thread_id: ThreadId,
+        name: String,
+    ) -> ActionResult<()> {
+        self.edit_forum_topic(forum, thread_id).name(name).await?;
+        Ok(())
+    }
+}
+
+/// A call that would have been made to Telegram
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordedAction {
+    SendText {
+        chat_id: ChatId,
+        text: String,
+    },
+    Forward {
+        to: ChatId,
+        message_id: MessageId,
+    },
+    React {
+        chat_id: ChatId,
+        message_id: MessageId,
+        emoji: Option<String>,
+    },
+    ChatAction {
+        chat_id: ChatId,
+        action: ChatAction,
+    },
+    Download {
+        file_id: String,
+    },
+    CreateTopic {
+        name: String,
+        icon_color: u32,
+    },
+    CloseTopic {
+        thread_id: ThreadId,
+    },
+    ReopenTopic {
+        thread_id: ThreadId,
+    },
+    RenameTopic {
+        thread_id: ThreadId,
+        name: String,
+    },
+}
+
+/// Call a [`DryRunBot`] can be told to fail, to exercise the error handling of the flows
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailingCall {
+    /// Fails like a chat without reactions
+    React,
+    Download,
+    /// Fails like a message deleted before it was forwarded
+    Forward,
+}
+
+/// Records all calls instead of sending them to Telegram
+#[derive(Clone, Debug, Default)]
+pub struct DryRunBot {
+    actions: Arc<Mutex<Vec<RecordedAction>>>,
+    next_id: Arc<AtomicI32>,
+    failing: Arc<Mutex<Vec<FailingCall>>>,
+}
+
+impl DryRunBot {
+    fn record(&self, action: RecordedAction) {
+        log::info!("[dry run] {:?}", action);
+        self.actions.lock().unwrap().push(action);
+    }
+
+    /// All calls recorded so far
+    pub fn recorded(&self) -> Vec<RecordedAction> {
+        self.actions.lock().unwrap().clone()
+    }
+
+    /// Fake id for messages and topics which would have been created
+    fn next_id(&self) -> i32 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Let all later calls of the kind `call` fail, they are still recorded
+    #[cfg(test)]
+    pub fn fail(&self, call: FailingCall) {
+        self.failing.lock().unwrap().push(call);
+    }
+
+    fn check(&self, call: FailingCall) -> ActionResult<()> {
+        if !self.failing.lock().unwrap().contains(&call) {
+            return Ok(());
+        }
+        Err(match call {
+            FailingCall::React => RequestError::Api(ApiError::Unknown(
+                "Bad Request: REACTION_INVALID".to_owned(),
+            ))
+            .into(),
+            FailingCall::Download => std::io::Error::other("download failed in dry run").into(),
+            FailingCall::Forward => RequestError::Api(ApiError::MessageToForwardNotFound).into(),
+        })
+    }
+}
+
+impl BotActions for DryRunBot {
+    async fn send_text(
+        &self,
+        chat_id: ChatId,
+        text: String,
+        _options: SendOptions,
+    ) -> ActionResult<MessageId> {
+        self.record(RecordedAction::SendText { chat_id, text });
+        Ok(MessageId(self.next_id()))
+    }
+
+    async fn forward(
+        &self,
+        to: ChatId,
+        _from: ChatId,
+        message_id: MessageId,
+        _thread_id: Option<ThreadId>,
+    ) -> ActionResult<MessageId> {
+        self.record(RecordedAction::Forward { to, message_id });
+        self.check(FailingCall::Forward)?;
+        Ok(MessageId(self.next_id()))
+    }
+
+    async fn react(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        emoji: Option<String>,
+    ) -> ActionResult<()> {
+        self.record(RecordedAction::React {
+            chat_id,
+            message_id,
+            emoji,
+        });
+        self.check(FailingCall::React)
+    }
+
+    async fn chat_action(&self, chat_id: ChatId, action: ChatAction) -> ActionResult<()> {
+        self.record(RecordedAction::ChatAction { chat_id, action });
+        Ok(())
+    }
+
+    async fn download(&self, file_id: String, destination: &Path) -> ActionResult<()> {
+        self.record(RecordedAction::Download { file_id });
+        self.check(FailingCall::Download)?;
+        // Leave an empty placeholder so the rest of the flow finds a file
+        fs::File::create(destination).await?;
+        Ok(())
+    }
+
+    async fn create_topic(
+        &self,
+        _forum: Recipient,
+        name: String,
+        icon_color: u32,
+    ) -> ActionResult<ThreadId> {
+        self.record(RecordedAction::CreateTopic { name, icon_color });
+        Ok(ThreadId(MessageId(self.next_id())))
+    }
+
+    async fn close_topic(&self, _forum: Recipient, thread_id: ThreadId) -> ActionResult<()> {
+        self.record(RecordedAction::CloseTopic { thread_id });
+        Ok(())
+    }
+
+    async fn reopen_topic(&self, _forum: Recipient, thread_id: ThreadId) -> ActionResult<()> {
+        self.record(RecordedAction::ReopenTopic { thread_id });
+        Ok(())
+    }
+
+    async fn rename_topic(
+        &self,
+        _forum: Recipient,
+        thread_id: ThreadId,
+        name: String,
+    ) -> ActionResult<()> {
+        self.record(RecordedAction::RenameTopic { thread_id, name });
+        Ok(())
+    }
+}
+
+/// Backend selected at startup, injected into the handlers
+#[derive(Clone, Debug)]
+pub enum Actions {
+    Telegram(Bot),
+    DryRun(DryRunBot),
+}
+
+impl BotActions for Actions {
+    async fn send_text(
+        &self,
+        chat_id: ChatId,
+        text: String,
+        options: SendOptions,
+    ) -> ActionResult<MessageId> {
+        match self {
+            Actions::Telegram(bot) => bot.send_text(chat_id, text, options).await,
+            Actions::DryRun(bot) => bot.send_text(chat_id, text, options).await,
+        }
+    }
+
+    async fn forward(
+        &self,
+        to: ChatId,
+        from: ChatId,
+        message_id: MessageId,
+        thread_id: Option<ThreadId>,
+    ) -> ActionResult<MessageId> {
+        match self {
+            Actions::Telegram(bot) => bot.forward(to, from, message_id, thread_id).await,
+            Actions::DryRun(bot) => bot.forward(to, from, message_id, thread_id).await,
+        }
+    }
+
+    async fn react(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        emoji: Option<String>,
+    ) -> ActionResult<()> {
+        match self {
+            Actions::Telegram(bot) => bot.react(chat_id, message_id, emoji).await,
+            Actions::DryRun(bot) => bot.react(chat_id, message_id, emoji).await,
+        }
+    }
+
+    async fn chat_action(&self, chat_id: ChatId, action: ChatAction) -> ActionResult<()> {
+        match self {
+            Actions::Telegram(bot) => bot.chat_action(chat_id, action).await,
+            Actions::DryRun(bot) => bot.chat_action(chat_id, action).await,
+        }
+    }
+
+    async fn download(&self, file_id: String, destination: &Path) -> ActionResult<()> {
+        match self {
+            Actions::Telegram(bot) => bot.download(file_id, destination).await,
+            Actions::DryRun(bot) => bot.download(file_id, destination).await,
+        }
+    }
+
+    async fn create_topic(
+        &self,
+        forum: Recipient,
+        name: String,
+        icon_color: u32,
+    ) -> ActionResult<ThreadId> {
+        match self {
+            Actions::Telegram(bot) => bot.create_topic(forum, name, icon_color).await,
+            Actions::DryRun(bot) => bot.create_topic(forum, name, icon_color).await,
+        }
+    }
+
+    async fn close_topic(&self, forum: Recipient, thread_id: ThreadId) -> ActionResult<()> {
+        match self {
+            Actions::Telegram(bot) => bot.close_topic(forum, thread_id).await,
+            Actions::DryRun(bot) => bot.close_topic(forum, thread_id).await,
+        }
+    }
+
+    async fn reopen_topic(&self, forum: Recipient, thread_id: ThreadId) -> ActionResult<()> {
+        match self {
+            Actions::Telegram(bot) => bot.reopen_topic(forum, thread_id).await,
+            Actions::DryRun(bot) => bot.reopen_topic(forum, thread_id).await,
+        }
+    }
+
+    async fn rename_topic(
+        &self,
+        forum: Recipient,
+        thread_id: ThreadId,
+        name: String,
+    ) -> ActionResult<()> {
+        match self {
+            Actions::Telegram(bot) => bot.rename_topic(forum, thread_id, name).await,
+            Actions::DryRun(bot) => bot.rename_topic(forum, thread_id, name).await,
+        }
+    }
+}
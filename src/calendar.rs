@@ -0,0 +1,123 @@
+//! Event days, which don't end at midnight so that nights out count to the previous day
+use chrono::{Duration, FixedOffset, NaiveDate, NaiveDateTime};
+
+/// Hour (UTC) at which a new event day starts unless configured otherwise
+pub const DEFAULT_ROLLOVER_HOUR: u32 = 6;
+
+/// Event day of `now`; times before `rollover_hour` belong to the previous day
+pub fn event_day(now: NaiveDateTime, rollover_hour: u32) -> NaiveDate {
+    (now - Duration::hours(rollover_hour.into())).date()
+}
+
+/// Start of the event day of `now`
+pub fn day_start(now: NaiveDateTime, rollover_hour: u32) -> NaiveDateTime {
+    event_day(now, rollover_hour)
+        .and_hms_opt(rollover_hour, 0, 0)
+        .expect("rollover hour must be below 24")
+}
+
+/// Start of the event day after the one of `now`
+pub fn next_day_start(now: NaiveDateTime, rollover_hour: u32) -> NaiveDateTime {
+    day_start(now, rollover_hour) + Duration::days(1)
+}
+
+/// Time left until `until` in days, hours and minutes, e.g. "1d 2h 5m"
+pub fn countdown(now: NaiveDateTime, until: NaiveDateTime) -> String {
+    let left = (until - now).max(Duration::zero());
+    let (days, hours, minutes) = (
+        left.num_days(),
+        left.num_hours() % 24,
+        left.num_minutes() % 60,
+    );
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes.max(1))
+    }
+}
+
+/// Time since `then`, e.g. "5 min ago" or "2 h ago"
+pub fn time_ago(now: NaiveDateTime, then: NaiveDateTime) -> String {
+    let elapsed = now - then;
+    if elapsed < Duration::minutes(1) {
+        "just now".to_owned()
+    } else if elapsed < Duration::hours(1) {
+        format!("{} min ago", elapsed.num_minutes())
+    } else if elapsed < Duration::days(1) {
+        format!("{} h ago", elapsed.num_hours())
+    } else {
+        format!("{} d ago", elapsed.num_days())
+    }
+}
+
+/// Parse a UTC offset such as `+01:00`, `+2` or `-05:30`
+pub fn parse_utc_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    let (sign, rest) = match value.chars().next()? {
+        '+' => (1, &value[1..]),
+        '-' => (-1, &value[1..]),
+        _ => (1, value),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let (hours, minutes) = (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?);
+    if !(0..60).contains(&minutes) {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// `time` (UTC) as local time of the event, e.g. "2024-11-20 19:00 (UTC+01:00)"
+pub fn format_local(time: NaiveDateTime, offset: FixedOffset) -> String {
+    format!(
+        "{} (UTC{})",
+        (time + offset).format("%Y-%m-%d %H:%M"),
+        offset
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn at(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn day_changes_at_rollover_hour() {
+        let before = at("2024-11-20 05:59:59");
+        let after = at("2024-11-20 06:00:01");
+        assert_eq!(event_day(before, 6), date("2024-11-19"));
+        assert_eq!(event_day(after, 6), date("2024-11-20"));
+        assert_eq!(day_start(before, 6), at("2024-11-19 06:00:00"));
+        assert_eq!(day_start(after, 6), at("2024-11-20 06:00:00"));
+        assert_eq!(next_day_start(before, 6), at("2024-11-20 06:00:00"));
+        assert_eq!(next_day_start(after, 6), at("2024-11-21 06:00:00"));
+    }
+
+    #[test]
+    fn rollover_hour_is_utc() {
+        // 06:30 in Berlin is 05:30 UTC, still before the reset
+        let local = DateTime::parse_from_rfc3339("2024-11-20T06:30:00+01:00").unwrap();
+        assert_eq!(event_day(local.naive_utc(), 6), date("2024-11-19"));
+        let local = DateTime::parse_from_rfc3339("2024-11-20T07:30:00+01:00").unwrap();
+        assert_eq!(event_day(local.naive_utc(), 6), date("2024-11-20"));
+    }
+
+    #[test]
+    fn midnight_belongs_to_previous_day() {
+        let midnight = at("2024-12-01 00:00:00");
+        assert_eq!(event_day(midnight, 6), date("2024-11-30"));
+        assert_eq!(day_start(midnight, 6), at("2024-11-30 06:00:00"));
+        assert_eq!(next_day_start(midnight, 6), at("2024-12-01 06:00:00"));
+        assert_eq!(event_day(midnight, 0), date("2024-12-01"));
+        assert_eq!(next_day_start(midnight, 0), at("2024-12-02 00:00:00"));
+    }
+}
@@ -0,0 +1,253 @@
+//! Encoding of the inline keyboard callback data
+//!
+//! Telegram limits callback data to 64 bytes, so challenges are referenced by their
+//! SQLite `rowid` instead of their (arbitrary length) name. All fields are numeric or
+//! fixed keywords, hence the `:` separator can't appear inside a field.
+use std::{error::Error, fmt, str::FromStr};
+
+/// Decision of a judge for a submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The submission completes the challenge with the given `rowid`
+    Challenge(i64),
+    Unclear,
+    Invalid,
+    /// Defer the decision, the submission stays pending
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallbackData {
+    Judge {
+        associate: u64,
+        submission: i32,
+        verdict: Verdict,
+    },
+    /// Accept the submission for a challenge with quality tiers, `tier` starts at 1
+    Tier {
+        associate: u64,
+        submission: i32,
+        challenge: i64,
+        tier: usize,
+    },
+    Profile {
+        associate: u64,
+    },
+    /// Show the challenge legend in place of the judging keyboard
+    Legend {
+        associate: u64,
+        submission: i32,
+    },
+    /// Restore the judging keyboard after the legend
+    Back {
+        associate: u64,
+        submission: i32,
+    },
+    /// Send a new judging keyboard for a disputed submission
+    Reopen {
+        associate: u64,
+        submission: i32,
+    },
+}
+
+impl fmt::Display for CallbackData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallbackData::Judge {
+                associate,
+                submission,
+                verdict,
+            } => {
+                write!(f, "judge:{}:{}:", associate, submission)?;
+                match verdict {
+                    Verdict::Challenge(id) => write!(f, "{}", id),
+                    Verdict::Unclear => write!(f, "unclear"),
+                    Verdict::Invalid => write!(f, "invalid"),
+                    Verdict::Flag => write!(f, "flag"),
+                }
+            }
+            CallbackData::Tier {
+                associate,
+                submission,
+                challenge,
+                tier,
+            } => write!(
+                f,
+                "tier:{}:{}:{}:{}",
+                associate, submission, challenge, tier
+            ),
+            CallbackData::Profile { associate } => write!(f, "profile:{}", associate),
+            CallbackData::Legend {
+                associate,
+                submission,
+            } => write!(f, "nav:legend:{}:{}", associate, submission),
+            CallbackData::Back {
+                associate,
+                submission,
+            } => write!(f, "nav:back:{}:{}", associate, submission),
+            CallbackData::Reopen {
+                associate,
+                submission,
+            } => write!(f, "reopen:{}:{}", associate, submission),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidCallbackData(pub String);
+
+impl fmt::Display for InvalidCallbackData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid callback data {:?}", self.0)
+    }
+}
+
+impl Error for InvalidCallbackData {}
+
+impl FromStr for CallbackData {
+    type Err = InvalidCallbackData;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCallbackData(s.to_owned());
+        let parts = s.split(':').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            ["judge", associate, submission, verdict] => Ok(CallbackData::Judge {
+                associate: associate.parse().map_err(|_| invalid())?,
+                submission: submission.parse().map_err(|_| invalid())?,
+                verdict: match *verdict {
+                    "unclear" => Verdict::Unclear,
+                    "invalid" => Verdict::Invalid,
+                    "flag" => Verdict::Flag,
+                    id => Verdict::Challenge(id.parse().map_err(|_| invalid())?),
+                },
+            }),
+            ["tier", associate, submission, challenge, tier] => Ok(CallbackData::Tier {
+                associate: associate.parse().map_err(|_| invalid())?,
+                submission: submission.parse().map_err(|_| invalid())?,
+                challenge: challenge.parse().map_err(|_| invalid())?,
+                tier: tier.parse().map_err(|_| invalid())?,
+            }),
+            ["profile", associate] => Ok(CallbackData::Profile {
+                associate: associate.parse().map_err(|_| invalid())?,
+            }),
+            ["nav", "legend", associate, submission] => Ok(CallbackData::Legend {
+                associate: associate.parse().map_err(|_| invalid())?,
+                submission: submission.parse().map_err(|_| invalid())?,
+            }),
+            ["nav", "back", associate, submission] => Ok(CallbackData::Back {
+                associate: associate.parse().map_err(|_| invalid())?,
+                submission: submission.parse().map_err(|_| invalid())?,
+            }),
+            ["reopen", associate, submission] => Ok(CallbackData::Reopen {
+                associate: associate.parse().map_err(|_| invalid())?,
+                submission: submission.parse().map_err(|_| invalid())?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: CallbackData, encoded: &str) {
+        assert_eq!(data.to_string(), encoded);
+        assert_eq!(encoded.parse::<CallbackData>().unwrap(), data);
+    }
+
+    #[test]
+    fn judge_verdicts_round_trip() {
+        let judge = |verdict| CallbackData::Judge {
+            associate: 42,
+            submission: 7,
+            verdict,
+        };
+        round_trip(judge(Verdict::Challenge(3)), "judge:42:7:3");
+        round_trip(judge(Verdict::Unclear), "judge:42:7:unclear");
+        round_trip(judge(Verdict::Invalid), "judge:42:7:invalid");
+        round_trip(judge(Verdict::Flag), "judge:42:7:flag");
+    }
+
+    #[test]
+    fn test_submissions_round_trip() {
+        // Test submissions have negative ids
+        round_trip(
+            CallbackData::Judge {
+                associate: 42,
+                submission: -1,
+                verdict: Verdict::Challenge(3),
+            },
+            "judge:42:-1:3",
+        );
+        round_trip(
+            CallbackData::Tier {
+                associate: 42,
+                submission: -1,
+                challenge: 3,
+                tier: 2,
+            },
+            "tier:42:-1:3:2",
+        );
+    }
+
+    #[test]
+    fn other_buttons_round_trip() {
+        round_trip(
+            CallbackData::Tier {
+                associate: 42,
+                submission: 7,
+                challenge: 3,
+                tier: 2,
+            },
+            "tier:42:7:3:2",
+        );
+        round_trip(CallbackData::Profile { associate: 42 }, "profile:42");
+        round_trip(
+            CallbackData::Legend {
+                associate: 42,
+                submission: 7,
+            },
+            "nav:legend:42:7",
+        );
+        round_trip(
+            CallbackData::Back {
+                associate: 42,
+                submission: 7,
+            },
+            "nav:back:42:7",
+        );
+        round_trip(
+            CallbackData::Reopen {
+                associate: 42,
+                submission: 7,
+            },
+            "reopen:42:7",
+        );
+    }
+
+    #[test]
+    fn old_and_truncated_data_is_invalid() {
+        for data in [
+            // Keyboards sent before the switch to rowids
+            "42###7###landmark",
+            "42###7###___unclear",
+            "profile###42",
+            // Cut off somewhere along the way
+            "",
+            "judge",
+            "judge:42:7",
+            "judge:42:7:",
+            "tier:42:7:3",
+            "nav:legend:42",
+            "reopen:42",
+            "profile:",
+            // Wrong field types
+            "judge:-42:7:3",
+            "judge:42:7:landmark",
+        ] {
+            let error = data.parse::<CallbackData>().unwrap_err();
+            assert_eq!(error.0, data);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Challenges sent by a maintainer as CSV or JSON document, upserted by name
+//!
+//! CSV needs a header row with the column names of the `challenges` table, JSON an array
+//! of objects with the same keys. Only `name` and `short_name` are required.
+use crate::scoring;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ImportedChallenge {
+    pub name: String,
+    pub short_name: String,
+    pub desc: Option<String>,
+    pub points: Option<i32>,
+    pub starts_at: Option<String>,
+    pub decay_half_life: Option<f64>,
+    pub decay_floor: Option<i32>,
+    pub emoji: Option<String>,
+    pub tiers: Option<String>,
+    pub prerequisite: Option<String>,
+    pub first_only: Option<bool>,
+    pub forum_thread_id: Option<i32>,
+    pub podium_bonus: Option<String>,
+}
+
+/// Format of an imported document, from the file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    pub fn from_file_name(name: &str) -> Option<Format> {
+        let name = name.to_lowercase();
+        if name.ends_with(".csv") {
+            Some(Format::Csv)
+        } else if name.ends_with(".json") {
+            Some(Format::Json)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse all challenges of a document. Any problem rejects the whole document, the errors
+/// name the line (CSV) or entry (JSON) they are in.
+pub fn parse(format: Format, content: &[u8]) -> Result<Vec<ImportedChallenge>, Vec<String>> {
+    let rows = match format {
+        Format::Csv => parse_csv(content)?,
+        Format::Json => parse_json(content)?,
+    };
+    let errors = rows
+        .iter()
+        .filter_map(|(position, challenge)| {
+            validate(challenge)
+                .err()
+                .map(|e| format!("{}: {}", position, e))
+        })
+        .collect::<Vec<String>>();
+    if errors.is_empty() {
+        Ok(rows.into_iter().map(|(_, challenge)| challenge).collect())
+    } else {
+        Err(errors)
+    }
+}
+
+fn parse_csv(content: &[u8]) -> Result<Vec<(String, ImportedChallenge)>, Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content);
+    let mut rows = vec![];
+    let mut errors = vec![];
+    for (index, record) in reader.deserialize::<ImportedChallenge>().enumerate() {
+        match record {
+            // Line 1 is the header
+            Ok(challenge) => rows.push((format!("Line {}", index + 2), challenge)),
+            Err(e) => {
+                let line = e
+                    .position()
+                    .map(|position| position.line())
+                    .unwrap_or(index as u64 + 2);
+                errors.push(format!("Line {}: {}", line, csv_error_message(&e)));
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Error without the position, which is reported separately
+fn csv_error_message(e: &csv::Error) -> String {
+    match e.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => match err.field() {
+            Some(field) => format!("column {}: {}", field + 1, err.kind()),
+            None => err.kind().to_string(),
+        },
+        _ => e.to_string(),
+    }
+}
+
+fn parse_json(content: &[u8]) -> Result<Vec<(String, ImportedChallenge)>, Vec<String>> {
+    let challenges: Vec<ImportedChallenge> = serde_json::from_slice(content)
+        // The message already names line and column
+        .map_err(|e| vec![e.to_string()])?;
+    Ok(challenges
+        .into_iter()
+        .enumerate()
+        .map(|(index, challenge)| (format!("Entry {}", index + 1), challenge))
+        .collect())
+}
+
+fn validate(challenge: &ImportedChallenge) -> Result<(), String> {
+    if challenge.name.trim().is_empty() {
+        return Err("name is empty".to_owned());
+    }
+    if challenge.short_name.trim().is_empty() {
+        return Err(format!("short_name of {:?} is empty", challenge.name));
+    }
+    if let Some(starts_at) = challenge.starts_at.as_deref() {
+        if scoring::parse_date(starts_at).is_none() {
+            return Err(format!(
+                "starts_at {:?} is not a date like 2024-11-20 18:00:00",
+                starts_at
+            ));
+        }
+    }
+    if let Some(tiers) = challenge.tiers.as_deref() {
+        if tiers.split(',').any(|x| x.trim().parse::<i32>().is_err()) {
+            return Err(format!(
+                "tiers {:?} are not comma separated points like 1,2,3",
+                tiers
+            ));
+        }
+    }
+    if let Some(podium_bonus) = challenge.podium_bonus.as_deref() {
+        if podium_bonus
+            .split(',')
+            .any(|x| x.trim().parse::<i32>().is_err())
+        {
+            return Err(format!(
+                "podium_bonus {:?} is not comma separated points like 5,3,1",
+                podium_bonus
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Expected entries of the `config` table and their formats, checked on startup
+use crate::{calendar, scoring};
+use std::path::Path;
+use url::Url;
+
+/// Format of a config value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigKind {
+    /// `file::<path>` or `url::<url>`
+    AssetSource,
+    /// Date as `2024-11-20 18:00:00`
+    Timestamp,
+    /// Non-negative whole number
+    Count,
+    /// Hour of the day, 0 to 23
+    Hour,
+    /// Offset from UTC such as `+01:00`
+    UtcOffset,
+    /// `true` or `false`
+    Bool,
+    /// Non-negative decimal such as `1.5`
+    Multiplier,
+    /// `html` or `markdownv2`
+    ParseMode,
+    /// Buttons per row of the judge keyboard, 1 to 4
+    KeyboardColumns,
+    /// Free text
+    Text,
+}
+
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub kind: ConfigKind,
+    /// Inserted on startup if the entry is missing
+    pub default: Option<&'static str>,
+}
+
+const fn key(name: &'static str, kind: ConfigKind, default: Option<&'static str>) -> ConfigKey {
+    ConfigKey {
+        name,
+        kind,
+        default,
+    }
+}
+
+/// Entries read by the bot. Those without a default are optional and fall back to the
+/// behavior documented at their use.
+pub const EXPECTED: &[ConfigKey] = &[
+    key(
+        "schedule_source",
+        ConfigKind::AssetSource,
+        Some(crate::DEFAULT_SCHEDULE_SOURCE),
+    ),
+    key(
+        "city_guide",
+        ConfigKind::AssetSource,
+        Some(crate::DEFAULT_SURVIVAL_GUIDE_SOURCE),
+    ),
+    key("event_start", ConfigKind::Timestamp, None),
+    key("submission_deadline", ConfigKind::Timestamp, None),
+    key("event_utc_offset", ConfigKind::UtcOffset, None),
+    key("max_submissions_per_user", ConfigKind::Count, None),
+    key("max_team_submissions_per_day", ConfigKind::Count, None),
+    key("daily_limit_reset_hour", ConfigKind::Hour, None),
+    key(
+        "pending_submissions_warning_threshold",
+        ConfigKind::Count,
+        None,
+    ),
+    key("pinned_scoreboard_message_id", ConfigKind::Count, None),
+    key("keep_full_size_photos", ConfigKind::Bool, None),
+    key("forums_closed", ConfigKind::Bool, None),
+    key("easter_eggs", ConfigKind::Bool, None),
+    key("ignore_unrecognized_text", ConfigKind::Bool, None),
+    key("max_caption_length", ConfigKind::Count, None),
+    key("min_video_duration_secs", ConfigKind::Count, None),
+    key("max_video_duration_secs", ConfigKind::Count, None),
+    key("video_points_multiplier", ConfigKind::Multiplier, None),
+    key("broadcast_parse_mode", ConfigKind::ParseMode, None),
+    key("judge_keyboard_columns", ConfigKind::KeyboardColumns, None),
+    key("schedule_reminder_minutes", ConfigKind::Count, None),
+    key("points_format", ConfigKind::Text, None),
+    key("submissions_disabled_message", ConfigKind::Text, None),
+    key("no_team_message", ConfigKind::Text, None),
+    key("personal_submission_limit_message", ConfigKind::Text, None),
+    key("challenge_completed_message", ConfigKind::Text, None),
+    key("rules", ConfigKind::Text, None),
+];
+
+/// Check `value` against the format of `kind`, describing the problem otherwise
+pub fn validate(kind: ConfigKind, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    match kind {
+        ConfigKind::AssetSource => match value.split_once("::") {
+            Some(("file", path)) if Path::new(path).is_file() => Ok(()),
+            Some(("file", path)) => Err(format!("file {:?} doesn't exist", path)),
+            Some(("url", url)) => Url::parse(url)
+                .map(|_| ())
+                .map_err(|e| format!("invalid URL: {}", e)),
+            _ => Err("expected file::<path> or url::<url>".to_owned()),
+        },
+        ConfigKind::Timestamp => scoring::parse_date(value)
+            .map(|_| ())
+            .ok_or_else(|| "expected a date like 2024-11-20 18:00:00".to_owned()),
+        ConfigKind::Count => value
+            .parse::<u32>()
+            .map(|_| ())
+            .map_err(|_| "expected a non-negative whole number".to_owned()),
+        ConfigKind::Hour => match value.parse::<u32>() {
+            Ok(hour) if hour < 24 => Ok(()),
+            _ => Err("expected an hour from 0 to 23".to_owned()),
+        },
+        ConfigKind::UtcOffset => calendar::parse_utc_offset(value)
+            .map(|_| ())
+            .ok_or_else(|| "expected an offset from UTC like +01:00".to_owned()),
+        ConfigKind::Bool => value
+            .parse::<bool>()
+            .map(|_| ())
+            .map_err(|_| "expected true or false".to_owned()),
+        ConfigKind::Multiplier => match value.parse::<f64>() {
+            Ok(multiplier) if multiplier >= 0.0 => Ok(()),
+            _ => Err("expected a non-negative number such as 1.5".to_owned()),
+        },
+        ConfigKind::ParseMode => match value.to_lowercase().as_str() {
+            "html" | "markdownv2" | "markdown" => Ok(()),
+            _ => Err("expected html or markdownv2".to_owned()),
+        },
+        ConfigKind::KeyboardColumns => match value.parse::<usize>() {
+            Ok(columns) if crate::JUDGE_KEYBOARD_COLUMNS.contains(&columns) => Ok(()),
+            _ => Err(format!(
+                "expected a number from {} to {}",
+                crate::JUDGE_KEYBOARD_COLUMNS.start(),
+                crate::JUDGE_KEYBOARD_COLUMNS.end()
+            )),
+        },
+        ConfigKind::Text => Ok(()),
+    }
+}
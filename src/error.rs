@@ -0,0 +1,64 @@
+//! Errors returned by the handlers
+use teloxide::{ApiError, DownloadError, RequestError};
+
+pub type BotResult<T = ()> = Result<T, BotError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BotError {
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Telegram request failed: {0}")]
+    Telegram(#[from] RequestError),
+    #[error("file download failed: {0}")]
+    Download(#[from] DownloadError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("unknown asset source {0:?}")]
+    UnknownAssetSource(String),
+    #[error("submission {0} not found")]
+    SubmissionNotFound(String),
+    /// Channel posts and anonymous group admins have no sender
+    #[error("message has no sender")]
+    NoSender,
+}
+
+impl BotError {
+    /// Coarse category used when logging
+    pub fn category(&self) -> &'static str {
+        match self {
+            BotError::Database(_) => "database",
+            BotError::Telegram(_) | BotError::Download(_) => "telegram",
+            BotError::Io(_) => "io",
+            BotError::Url(_) | BotError::UnknownAssetSource(_) => "config",
+            BotError::SubmissionNotFound(_) | BotError::NoSender => "domain",
+        }
+    }
+
+    /// The recipient blocked the bot, deleted their account or never started a chat with it
+    pub fn is_unreachable_user(&self) -> bool {
+        matches!(
+            self,
+            BotError::Telegram(RequestError::Api(
+                ApiError::BotBlocked
+                    | ApiError::UserDeactivated
+                    | ApiError::CantInitiateConversation
+            ))
+        )
+    }
+
+    /// The chat doesn't allow the reaction or the message is too old to react to. Telegram has
+    /// no dedicated error codes for these, only the description.
+    pub fn is_reaction_unsupported(&self) -> bool {
+        match self {
+            BotError::Telegram(RequestError::Api(ApiError::Unknown(description))) => {
+                let description = description.to_uppercase();
+                description.contains("REACTION_INVALID")
+                    || description.contains("MESSAGE CAN'T BE REACTED")
+                    || description.contains("REACTIONS ARE DISABLED")
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Translations of the `/help` text
+//!
+//! The command menu keeps the English descriptions from the `#[command(description)]`
+//! attributes; `/help` uses the catalog of the user's language where available.
+use teloxide::utils::command::BotCommands;
+
+/// German descriptions, keyed by command without the `/`
+const GERMAN: &[(&str, &str)] = &[
+    (
+        "join_team",
+        "Einem Team über Name oder Beitrittscode beitreten. Z.B. /join_team team123",
+    ),
+    ("team_overview", "Zeigt die Mitglieder deines Teams."),
+    (
+        "team_emoji",
+        "Das Emoji deines Teams festlegen. Z.B. /team_emoji 🦄",
+    ),
+    ("score", "Zeigt den Punktestand deines Teams."),
+    (
+        "time_left",
+        "Zeigt, wie lange noch Einsendungen angenommen werden.",
+    ),
+    (
+        "challenges",
+        "Zeigt die Challenges, die dein Team noch nicht geschafft hat.",
+    ),
+    (
+        "withdraw_submission",
+        "Eine noch nicht bewertete Einsendung zurückziehen. Z.B. /withdraw_submission 1234",
+    ),
+    (
+        "feedback",
+        "Feedback an die Organisatoren senden, optional zu einer Einsendung. Z.B. /feedback 1234 Das war die Landmark-Challenge",
+    ),
+    (
+        "mute",
+        "Antworten auf lockere Nachrichten aus- oder wieder einschalten.",
+    ),
+    (
+        "mute_results",
+        "Nachrichten zu geschafften Challenges aus- oder wieder einschalten, die Reaktionen bleiben.",
+    ),
+    (
+        "rules",
+        "Die Regeln: Punkte, Teams und Verhalten.",
+    ),
+    (
+        "emergency_information",
+        "Aktuelles Safety-Team und Notrufnummern.",
+    ),
+    ("survival_guide", "Den Survival Guide erhalten."),
+    ("schedule", "Zeigt den Zeitplan."),
+    ("help", "Zeigt diese Nachricht."),
+];
+
+fn catalog(language: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    match language {
+        "de" => Some(GERMAN),
+        _ => None,
+    }
+}
+
+/// Help text for the commands of `C` in the language of a Telegram `language_code` such as
+/// `de-AT`, or `None` without a catalog for it. Untranslated commands keep their English
+/// description.
+pub fn help_text<C: BotCommands>(
+    language_code: Option<&str>,
+    bot_username: Option<&str>,
+) -> Option<String> {
+    let language = language_code?.split('-').next()?;
+    let catalog = catalog(language)?;
+    let mention = bot_username.map(|x| format!("@{}", x)).unwrap_or_default();
+    let text = C::bot_commands()
+        .into_iter()
+        .map(|command| {
+            let name = command.command.trim_start_matches('/');
+            let description = catalog
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, description)| description.to_string())
+                .unwrap_or(command.description);
+            format!("{}{} — {}", command.command, mention, description)
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    Some(text)
+}
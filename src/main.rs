@@ -1,48 +1,134 @@
-use chrono::Timelike;
 use serde::{Deserialize, Serialize};
 use sqlx::{migrate::MigrateDatabase, SqlitePool};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     env,
     error::Error,
-    path::Path,
+    path::{Path, PathBuf},
     sync::atomic::{AtomicBool, Ordering},
 };
 use teloxide::{
     dispatching::{HandlerExt, UpdateFilterExt},
     dptree,
+    net::Download,
     prelude::{Dispatcher, *},
     types::{
-        CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, MediaVideo, Message,
-        MessageId, ParseMode, ReactionType, Recipient, ThreadId, Update,
+        CallbackQuery, Chat, ChatKind, ChatPublic, InlineKeyboardButton, InlineKeyboardMarkup,
+        InputFile, InputMedia, InputMediaPhoto, InputMediaVideo, MediaVideo, Message, MessageId,
+        MessageReactionUpdated, ParseMode, PublicChatKind, PublicChatSupergroup, ReactionType,
+        Recipient, ThreadId, Update,
     },
+    ApiError, RequestError,
 };
 use teloxide::{
-    net::Download,
     types::{MediaKind, MediaPhoto, MessageCommon, MessageKind, ReplyParameters},
-    utils::command::BotCommands,
+    utils::{
+        command::{BotCommands, ParseError},
+        html, markdown,
+    },
 };
 use tokio::fs;
 use url::Url;
+mod actions;
+mod calendar;
+mod callback;
+mod challenge_import;
+mod config;
+mod error;
+mod i18n;
+mod metadata;
 mod model;
+mod prerequisites;
+mod scoring;
+mod thumbnail;
+use actions::{Actions, BotActions, DryRunBot, RecordedAction, SendOptions};
+use callback::{CallbackData, Verdict};
+use error::{BotError, BotResult};
 use model::*;
+use rand::seq::SliceRandom;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Directory the media of submissions is downloaded to unless `SUBMISSIONS_DIR` is set
+const DEFAULT_SUBMISSIONS_DIR: &str = "./submissions";
+/// Submission files downloaded at the same time unless `MAX_CONCURRENT_DOWNLOADS` is set
+const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 4;
+/// Longest team name or file id that goes into the name of a downloaded file
+const MAX_FILE_NAME_PART_LENGTH: usize = 40;
+
+/// Seconds between background forum reconciliations, 0 only reconciles at startup
+const DEFAULT_FORUM_RECONCILE_INTERVAL_SECS: u64 = 600;
+/// How often the schedule is checked for events to remind participants of
+const SCHEDULE_REMINDER_CHECK_INTERVAL_SECS: u64 = 60;
+/// Minutes before an event its reminder goes out unless `schedule_reminder_minutes` is set
+const DEFAULT_SCHEDULE_REMINDER_MINUTES: i64 = 30;
+
+/// Telegram allows bots about 20 messages per minute in a group
+const JUDGE_CHAT_MESSAGES_PER_MINUTE: u64 = 20;
+/// Forward, details and keyboard
+const MESSAGES_PER_SUBMISSION: u64 = 3;
+
+/// Forum used for the team topics unless `FORUM_CHAT_ID` is set
+const DEFAULT_FORUM_CHAT: &str = "@esn_tumi_spreebreak_24ws_admin";
+
 #[derive(Clone)]
 struct ConfigParameters {
     maintainers: HashSet<UserId>,
+    /// First judge chat, which also holds the pinned scoreboard
     judge_chat: ChatId,
+    /// All judge chats, submissions are split between them by team
+    judge_chats: Vec<ChatId>,
+    /// Forum supergroup holding one topic per team; the ids in `forums` are its threads
+    forum_chat: Recipient,
+    /// Whether the judge chat is the team forum, so submissions can go to the team's topic
+    judge_chat_is_forum: bool,
+    /// Directory the media of submissions is downloaded to
+    submissions_dir: PathBuf,
+}
+
+impl ConfigParameters {
+    fn is_judge_chat(&self, chat_id: ChatId) -> bool {
+        self.judge_chats.contains(&chat_id)
+    }
+
+    /// Judge chat for the submissions of `team`, the same one for all of a team's submissions
+    fn judge_chat_for(&self, team: &str) -> ChatId {
+        self.judge_chats[team_hash(team) as usize % self.judge_chats.len()]
+    }
+
+    /// Judge chat a submission was forwarded to, as stored in `submissions.judge_chat_id`
+    fn stored_judge_chat(&self, judge_chat_id: Option<i64>) -> ChatId {
+        judge_chat_id.map(ChatId).unwrap_or(self.judge_chat)
+    }
+}
+
+/// Parse a chat given either as numeric id or as `@username`
+fn parse_recipient(chat: &str) -> Recipient {
+    match chat.parse::<i64>() {
+        Ok(id) => Recipient::Id(ChatId(id)),
+        Err(_) => Recipient::ChannelUsername(chat.to_owned()),
+    }
+}
+
+fn is_forum(chat: &Chat) -> bool {
+    matches!(
+        &chat.kind,
+        ChatKind::Public(ChatPublic {
+            kind: PublicChatKind::Supergroup(PublicChatSupergroup { is_forum: true, .. }),
+            ..
+        })
+    )
 }
 
 async fn init_db(db_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    if !sqlx::Sqlite::database_exists(db_url).await? {
+        sqlx::Sqlite::create_database(db_url).await?;
+    }
+
     let pool = SqlitePool::connect(db_url)
         .await
         .expect("Failed to connect to database");
-
-    if !sqlx::Sqlite::database_exists(&db_url).await? {
-        sqlx::Sqlite::create_database(&db_url).await?;
-    }
+    sqlx::migrate!().run(&pool).await?;
 
     Ok(pool)
 }
@@ -54,17 +140,47 @@ enum ParticipantCommand {
     Start,
 
     #[command(
-        description = "Join a team. E.g. /join_team team123",
+        description = "Join a team by name or join code. E.g. /join_team team123",
         parse_with = "default"
     )]
     #[command(alias = "join")]
     JoinTeam(String),
     #[command(description = "Show the team members.")]
     TeamOverview,
+    #[command(
+        description = "Set your team's emoji. E.g. /team_emoji 🦄",
+        parse_with = "default"
+    )]
+    TeamEmoji(String),
     #[command(description = "Shows your team score.")]
     Score,
+    #[command(description = "Shows how long submissions are still open.")]
+    TimeLeft,
+    #[command(description = "List the challenges your team hasn't completed yet.")]
+    Challenges,
+    #[command(
+        description = "Withdraw a submission that wasn't judged yet. E.g. /withdraw_submission 1234"
+    )]
+    WithdrawSubmission { message_id: i32 },
+    #[command(
+        description = "Send feedback to the organizers, optionally about a submission. E.g. /feedback 1234 This was the landmark challenge",
+        parse_with = parse_feedback
+    )]
+    Feedback {
+        submission: Option<i32>,
+        text: String,
+    },
+
+    #[command(description = "Turn replies to casual messages off or back on.")]
+    Mute,
+    #[command(
+        description = "Turn messages about completed challenges off or back on, the reactions stay."
+    )]
+    MuteResults,
 
     // Misc help functions for Spree Break
+    #[command(description = "Rules of the hunt: scoring, teams and conduct.")]
+    Rules,
     #[command(description = "Current safety team and emergency numbers.")]
     EmergencyInformation,
     #[command(description = "Get the survival guide.")]
@@ -82,34 +198,393 @@ enum ParticipantCommand {
 enum MaintainerCommands {
     #[command(description = "Enable or disable submissions")]
     EnableSubmissions { status: bool },
+    #[command(
+        description = "Set the start of the hunt (UTC), e.g. /set_event_start 2024-11-20 18:00:00, or /set_event_start none",
+        parse_with = "default"
+    )]
+    SetEventStart(String),
+    #[command(
+        description = "Add an event to the schedule in local time, participants are reminded before it, e.g. /schedule_event 2024-11-21 10:00 Walking tour. Without arguments, list the schedule",
+        parse_with = "default"
+    )]
+    ScheduleEvent(String),
+    #[command(description = "Remove an event from the schedule, e.g. /delete_schedule_event 3")]
+    DeleteScheduleEvent { id: i64 },
 
     #[command(description = "List teams without team members")]
     ListTeams,
+    #[command(
+        description = "Create a team with a join code, e.g. /create_team Team Rocket",
+        parse_with = "default"
+    )]
+    CreateTeam(String),
     #[command(description = "List teams and their respective members")]
     ListTeamMembers,
     #[command(description = "Leaderboard")]
     Scoreboard,
+    #[command(description = "Teams ranked by the number of different challenges completed")]
+    ChallengeLeaderboard,
+    #[command(description = "Post the scoreboard to the judge chat and pin it, kept up to date")]
+    PinScoreboard,
     #[command(description = "[CAUTION] List submission for each team")]
     ListTeamSubmissions,
+    #[command(
+        description = "List one participant's own submissions and their verdicts, e.g. /user_submissions @ash"
+    )]
+    UserSubmissions { username: String },
     #[command(description = "[CAUTION] List judged submission for each team")]
     ListTeamSubmissionJudgments,
+    #[command(description = "Recompute team scores and check judgements for inconsistencies")]
+    RecomputeScores,
+    #[command(
+        description = "List the challenges a team hasn't completed yet, e.g. /team_remaining Team Rocket",
+        parse_with = "default"
+    )]
+    TeamRemaining { team: String },
+    #[command(description = "Number of teams that completed each challenge")]
+    ChallengeStats,
+    #[command(description = "Number of judgements and acceptance rate of each judge")]
+    JudgeStats,
+    #[command(description = "Show all entries of the config table")]
+    ListConfig,
+    #[command(
+        description = "Add points to (or remove from) a team, e.g. /adjust_score \"Team Rocket\" -2 Late to the finish",
+        parse_with = parse_team_points_reason
+    )]
+    AdjustScore {
+        team: String,
+        points: i32,
+        reason: String,
+    },
     #[command(description = "Force update team forums")]
     UpdateTeamForums,
+    #[command(
+        description = "Close the forum topic of a team",
+        parse_with = "default"
+    )]
+    CloseForum { team: String },
+    #[command(
+        description = "Reopen the forum topic of a team",
+        parse_with = "default"
+    )]
+    ReopenForum { team: String },
+    #[command(
+        description = "Close the topics of all teams at the end of the event, confirm with /close_all_forums confirm",
+        parse_with = "default"
+    )]
+    CloseAllForums(String),
+    #[command(
+        description = "[CAUTION] Delete all submissions and judgements of a team, e.g. /reset_team \"Team Rocket\"",
+        parse_with = parse_team_confirmation
+    )]
+    ResetTeam { team: String, confirmation: String },
+    #[command(
+        description = "Post all photos and videos of a team into its forum topic, e.g. /post_team_gallery Team Rocket",
+        parse_with = "default"
+    )]
+    PostTeamGallery(String),
+
+    #[command(description = "Teams by their latest submission, quiet teams last")]
+    RecentActivity,
+    #[command(
+        description = "Participants who haven't written to the bot for the given hours, e.g. /inactive_users 12"
+    )]
+    InactiveUsers { hours: i64 },
+
+    #[command(
+        description = "Move a participant's submissions and team to their other account, e.g. /merge_users 123 456"
+    )]
+    MergeUsers { from_id: i64, into_id: i64 },
+
+    #[command(description = "List participants that switched teams")]
+    ListTeamChanges,
+
+    #[command(
+        description = "Delete downloaded submission files older than the given days, e.g. /cleanup_files 7, or /cleanup_files 7 true to only delete judged ones",
+        parse_with = parse_cleanup_files
+    )]
+    CleanupFiles {
+        older_than_days: i64,
+        only_judged: bool,
+    },
+    #[command(description = "Send yourself a copy of the database")]
+    BackupDb,
+
+    #[command(
+        description = "Send each team its submissions and score breakdown, confirm with /send_team_results confirm",
+        parse_with = "default"
+    )]
+    SendTeamResults(String),
 
     #[command(description = "Send a message to all users", parse_with = "default")]
     MessageToParticipants(String),
+    #[command(
+        description = "Send a message to one participant, e.g. /message_user @ash Please come to the info desk",
+        parse_with = parse_username_message
+    )]
+    MessageUser { username: String, message: String },
+    #[command(
+        description = "Send a broadcast only to yourself to check how it renders",
+        parse_with = "default"
+    )]
+    PreviewBroadcast { message: String },
+
+    #[command(
+        description = "Tell members of teams with unjudged submissions that results are coming, optionally with a custom message",
+        parse_with = "default"
+    )]
+    MessageToPendingTeams(String),
+
+    #[command(description = "Send the schedule and survival guide to all participants")]
+    PushAssets,
 
     #[command(description = "List participants")]
     ListParticipants,
 
-    #[command(description = "Rate a submission")]
+    #[command(
+        description = "Rate a submission by challenge name or its number on the judge keyboard, e.g. /judge 1234 3"
+    )]
     Judge { image_ref: i32, challenge: String },
+    #[command(
+        description = "Rate a range of a team's submissions as one challenge, e.g. /judge_team \"Team Rocket\" landmark 1200 1210",
+        parse_with = parse_judge_team
+    )]
+    JudgeTeam {
+        team: String,
+        challenge: String,
+        from_id: i32,
+        to_id: i32,
+    },
+    #[command(
+        description = "Post the judge keyboard for a submission again, e.g. /reopen_judging 1234"
+    )]
+    ReopenJudging { message_id: i32 },
+    #[command(
+        description = "Post a fake submission of a team to the judge chat to try judging, never scored, e.g. /test_submission Team Rocket",
+        parse_with = "default"
+    )]
+    TestSubmission { team: String },
+    #[command(
+        description = "Store, judge and score a submission of a made-up team without sending anything, then remove it again"
+    )]
+    SelfTest,
+    #[command(
+        description = "Send yourself the photo or video of a submission, e.g. /fetch_submission_media 1234"
+    )]
+    FetchSubmissionMedia { message_id: i32 },
 
     #[command(description = "[CAUTION] List submissions")]
     ListSubmissions,
+    #[command(
+        description = "Find submissions whose caption contains a keyword",
+        parse_with = "default"
+    )]
+    SearchSubmissions { keyword: String },
 
     #[command(description = "[CAUTION] List judgements")]
     ListJudgements,
+    #[command(description = "List submissions flagged for review")]
+    ListFlaggedSubmissions,
+}
+
+/// Pseudo challenges used by judges to reject a submission
+fn special_challenge(name: &str) -> Option<Challenge> {
+    let short_name = match name {
+        "___unclear" => "Unclear",
+        "___invalid" => "Invalid",
+        _ => return None,
+    };
+    Some(Challenge {
+        name: name.to_owned(),
+        short_name: short_name.to_owned(),
+        ..Default::default()
+    })
+}
+
+/// Split off a leading team name, which needs to be quoted if it contains spaces
+fn split_team_argument(input: &str) -> Result<(String, &str), ParseError> {
+    let input = input.trim_start();
+    let (team, rest) = match input.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"').ok_or_else(|| {
+            ParseError::IncorrectFormat("Missing closing quote of the team name".into())
+        })?,
+        None => input.split_once(' ').unwrap_or((input, "")),
+    };
+    if team.is_empty() {
+        return Err(ParseError::TooFewArguments {
+            expected: 1,
+            found: 0,
+            message: "Missing team name".to_owned(),
+        });
+    }
+    Ok((team.to_owned(), rest.trim_start()))
+}
+
+/// Parse `<team> <points> <reason...>`
+fn parse_team_points_reason(input: String) -> Result<(String, i32, String), ParseError> {
+    let (team, rest) = split_team_argument(&input)?;
+    let (points, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+    let points = points
+        .parse::<i32>()
+        .map_err(|e| ParseError::IncorrectFormat(e.into()))?;
+    if reason.trim().is_empty() {
+        return Err(ParseError::Custom("Please provide a reason".into()));
+    }
+    Ok((team, points, reason.trim().to_owned()))
+}
+
+/// Parse `<team> <challenge> <from id> <to id>`, the challenge may contain spaces
+fn parse_judge_team(input: String) -> Result<(String, String, i32, i32), ParseError> {
+    let (team, rest) = split_team_argument(&input)?;
+    let mut parts = rest.trim_end().rsplitn(3, ' ');
+    let (Some(to_id), Some(from_id), Some(challenge)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ParseError::Custom(
+            "Expected a challenge and the first and last submission ID".into(),
+        ));
+    };
+    let parse_id = |id: &str| {
+        id.parse::<i32>()
+            .map_err(|e| ParseError::IncorrectFormat(e.into()))
+    };
+    Ok((
+        team,
+        challenge.trim().to_owned(),
+        parse_id(from_id)?,
+        parse_id(to_id)?,
+    ))
+}
+
+/// `<team> [confirmation...]`
+fn parse_team_confirmation(input: String) -> Result<(String, String), ParseError> {
+    let (team, rest) = split_team_argument(&input)?;
+    Ok((team, rest.trim().to_owned()))
+}
+
+/// `<days> [only judged]`, all old files are deleted unless `only judged` is `true`
+fn parse_cleanup_files(input: String) -> Result<(i64, bool), ParseError> {
+    let mut parts = input.split_whitespace();
+    let days = parts
+        .next()
+        .ok_or_else(|| ParseError::Custom("Expected the age in days".into()))?
+        .parse::<i64>()
+        .map_err(|e| ParseError::IncorrectFormat(e.into()))?;
+    let only_judged = match parts.next() {
+        Some(only_judged) => only_judged
+            .parse::<bool>()
+            .map_err(|e| ParseError::IncorrectFormat(e.into()))?,
+        None => false,
+    };
+    match parts.next() {
+        Some(_) => Err(ParseError::TooManyArguments {
+            expected: 2,
+            found: 3 + parts.count(),
+            message: "Expected the age in days and optionally true or false".to_owned(),
+        }),
+        None => Ok((days, only_judged)),
+    }
+}
+
+/// `@username message`, the `@` is optional
+fn parse_username_message(input: String) -> Result<(String, String), ParseError> {
+    let Some((username, message)) = input.trim().split_once(char::is_whitespace) else {
+        return Err(ParseError::Custom(
+            "Expected a username followed by the message".into(),
+        ));
+    };
+    Ok((
+        username.trim_start_matches('@').to_owned(),
+        message.trim().to_owned(),
+    ))
+}
+
+/// `[submission id] <text>`, the text may refer to a submission by its ID
+fn parse_feedback(input: String) -> Result<(Option<i32>, String), ParseError> {
+    let input = input.trim();
+    let (submission, text) = match input.split_once(char::is_whitespace) {
+        Some((id, text)) if id.parse::<i32>().is_ok() => (id.parse().ok(), text.trim()),
+        _ => (None, input),
+    };
+    if text.is_empty() {
+        return Err(ParseError::Custom(
+            "Please add your feedback, e.g. /feedback 1234 This was the landmark challenge".into(),
+        ));
+    }
+    Ok((submission, text.to_owned()))
+}
+
+/// Team scores from valid judgements plus manual adjustments, best team first
+const TEAM_SCORES_QUERY: &str = "SELECT team, SUM(points) AS score
+    FROM (
+        SELECT s.team, j.points
+        FROM judgement j
+        JOIN submissions s ON j.submission_id = s.message_id
+        WHERE j.valid = 1
+        UNION ALL
+        SELECT team, points FROM adjustments
+    )
+    GROUP BY team
+    ORDER BY score DESC";
+
+/// Current score of a single team, 0 for unknown teams
+async fn team_score(pool: &SqlitePool, team: &str) -> Result<i64, sqlx::Error> {
+    let score: Option<i64> = sqlx::query_scalar(&format!(
+        "SELECT score FROM ({}) WHERE team = $1",
+        TEAM_SCORES_QUERY
+    ))
+    .bind(team)
+    .fetch_optional(pool)
+    .await?;
+    Ok(score.unwrap_or(0))
+}
+
+/// `value` reduced to characters that are safe in file names on every platform
+fn file_name_part(value: &str) -> String {
+    let part = value
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' => c,
+            _ => '_',
+        })
+        .take(MAX_FILE_NAME_PART_LENGTH)
+        .collect::<String>();
+    match part.trim_matches('_') {
+        "" => "unknown".to_owned(),
+        part => part.to_owned(),
+    }
+}
+
+/// User who sent `msg`, an error for messages without one
+fn sender(msg: &Message) -> BotResult<&teloxide::types::User> {
+    msg.from.as_ref().ok_or(BotError::NoSender)
+}
+
+/// How far `team` is from the team ranked right above it in `ranking`
+fn score_gap(ranking: &[TeamScore], team: &str, points_format: &str) -> String {
+    // Teams without judgements or adjustments aren't ranked yet, they are at 0 points
+    let (position, score) = ranking
+        .iter()
+        .position(|x| x.team == team)
+        .map(|position| (position, ranking[position].score))
+        .unwrap_or((ranking.len(), 0));
+    let tied = ranking.iter().find(|x| x.team != team && x.score == score);
+    let ahead = ranking[..position].iter().rev().find(|x| x.score > score);
+    match (ahead, tied) {
+        (None, None) => "🥇 Your team is in first place!".to_owned(),
+        (None, Some(tied)) => format!(
+            "🥇 Your team shares first place with team {}, one more point takes the lead",
+            tied.team
+        ),
+        (Some(_), Some(tied)) => format!(
+            "You're tied with team {}, one more point to overtake them",
+            tied.team
+        ),
+        (Some(ahead), None) => format!(
+            "You need {} more to overtake team {}",
+            format_points(ahead.score - score + 1, points_format),
+            ahead.team
+        ),
+    }
 }
 
 fn submission_message(sub: &SubmissionExtended) -> String {
@@ -122,1066 +597,7163 @@ fn submission_message(sub: &SubmissionExtended) -> String {
         sub.team,
         datetime,
         Some(sub.caption.clone())
-            .map(|x| if x.len() == 0 { "N/P".to_owned() } else { x })
+            .map(|x| if x.is_empty() { "N/P".to_owned() } else { x })
             .unwrap(),
         sub.message_id,
     )
 }
 
-async fn update_teams_in_forum(
-    bot: &Bot,
-    pool: &SqlitePool,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let teams: HashSet<_> =
-        sqlx::query_as::<_, Team>("SELECT DISTINCT team, COUNT(*) AS count FROM users")
-            .fetch_all(pool)
-            .await
-            .unwrap()
-            .iter()
-            .map(|x| x.team.clone())
-            .collect();
-    let teams_in_forum = sqlx::query_as::<_, Forum>("SELECT DISTINCT id, name FROM forums")
-        .fetch_all(pool)
-        .await
-        .unwrap();
-
-    let forum_team_names: HashSet<_> = teams_in_forum
-        .clone()
-        .iter()
-        .map(|x| x.name.to_owned())
-        .collect();
-    let forums_to_create: HashSet<_> = teams
-        .clone()
-        .into_iter()
-        .filter(|team| !forum_team_names.contains(team))
-        .collect();
-    let forums_to_close = teams_in_forum
-        .into_iter()
-        .filter(|team| !teams.contains(&team.name.clone()))
-        .collect::<HashSet<Forum>>();
-
-    let new_teams_futures = forums_to_create.iter().map(|team| async {
-        let topic = bot
-            .create_forum_topic(
-                Recipient::ChannelUsername("@esn_tumi_spreebreak_24ws_admin".to_owned()),
-                team.to_owned(),
-                7322096,
-                "🔥",
-            )
-            .await?;
-        log::warn!("{:?}", topic);
+const DEFAULT_SCHEDULE_SOURCE: &str = "file::assets/schedule.png";
+const DEFAULT_SURVIVAL_GUIDE_SOURCE: &str = "file::assets/survival_guide.pdf";
 
-        sqlx::query("INSERT INTO forums (id, name) VALUES ($1, $2)")
-            .bind(topic.thread_id.0 .0)
-            .bind(team.to_owned())
-            .execute(pool)
-            .await?;
+/// Resolve an asset configured as `file::<path>` or `url::<url>` in the `config` table
+async fn asset_file(pool: &SqlitePool, config_name: &str, default: &str) -> BotResult<InputFile> {
+    let source = config_value(pool, config_name)
+        .await?
+        .unwrap_or(default.to_owned());
+    log::trace!("Load asset config {} = {:?}", config_name, source);
+    let file = match source.split_once("::") {
+        Some(("file", path)) => InputFile::file(Path::new(path)),
+        Some(("url", path)) => InputFile::url(Url::parse(path)?),
+        _ => return Err(BotError::UnknownAssetSource(source)),
+    };
+    Ok(file)
+}
 
-        log::warn!("Created {:?}", team.to_owned());
-        Result::<_, Box<dyn Error + Send + Sync>>::Ok((topic.thread_id.0 .0, team.to_owned()))
-    });
-    let _ = futures::future::join_all(new_teams_futures).await;
+/// Check the entries of the `config` table and insert defaults for missing ones. Returns
+/// one line per problem instead of stopping at the first.
+async fn check_config(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    let mut report = vec![];
+    for key in config::EXPECTED {
+        match (config_value(pool, key.name).await?, key.default) {
+            (Some(value), _) => {
+                if let Err(problem) = config::validate(key.kind, &value) {
+                    report.push(format!("{} = {:?}: {}", key.name, value, problem));
+                }
+            }
+            (None, Some(default)) => {
+                set_config_value(pool, key.name, default).await?;
+                report.push(format!("{} missing, set to {:?}", key.name, default));
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(report)
+}
 
-    let close_forum_topics_futures = forums_to_close.iter().map(|thread| async {
-        log::warn!("Remove {:?}", thread.to_owned());
-        // bot.delete_forum_topic(
-        bot.close_forum_topic(
-            Recipient::ChannelUsername("@esn_tumi_spreebreak_24ws_admin".to_owned()),
-            ThreadId(MessageId(thread.id)),
-        )
+/// Value of an entry in the `config` table, if set
+async fn config_value(pool: &SqlitePool, name: &str) -> Result<Option<String>, sqlx::Error> {
+    let config = sqlx::query_as::<_, Config>("SELECT name, value FROM config WHERE name = $1")
+        .bind(name)
+        .fetch_optional(pool)
         .await?;
+    Ok(config.map(|x| x.value))
+}
 
-        // sqlx::query("DELETE FROM forums WHERE id = $1")
-        sqlx::query("UPDATE forums SET open = false WHERE id = $1")
-            .bind(thread.id)
-            .execute(pool)
-            .await?;
-        log::warn!("Deleted topic {:?}", thread.to_owned());
-        Result::<_, Box<dyn Error + Send + Sync>>::Ok(())
-    });
-    let _ = futures::future::join_all(close_forum_topics_futures).await;
+/// Start of the hunt from the `event_start` config (UTC), `None` if unset
+async fn event_start(pool: &SqlitePool) -> Result<Option<chrono::NaiveDateTime>, sqlx::Error> {
+    Ok(config_value(pool, "event_start")
+        .await?
+        .and_then(|x| scoring::parse_date(&x)))
+}
 
-    Ok(())
+/// End of submissions from the `submission_deadline` config (UTC), `None` if unset
+async fn submission_deadline(
+    pool: &SqlitePool,
+) -> Result<Option<chrono::NaiveDateTime>, sqlx::Error> {
+    Ok(config_value(pool, "submission_deadline")
+        .await?
+        .and_then(|x| scoring::parse_date(&x)))
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-enum Media {
-    Photo(MediaPhoto),
-    Video(MediaVideo),
+/// Offset of the event's local time from the `event_utc_offset` config, UTC if unset.
+/// Times are stored in UTC and only shown in local time.
+async fn event_utc_offset(pool: &SqlitePool) -> Result<chrono::FixedOffset, sqlx::Error> {
+    Ok(config_value(pool, "event_utc_offset")
+        .await?
+        .and_then(|x| calendar::parse_utc_offset(&x))
+        .unwrap_or(chrono::FixedOffset::east_opt(0).unwrap()))
 }
 
-async fn receive_submission(
-    media: Media,
-    msg: Message,
-    bot: Bot,
-    cfg: ConfigParameters,
-    pool: SqlitePool,
-    submissions_enabled: Arc<AtomicBool>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if !submissions_enabled.load(Ordering::Relaxed) {
-        bot.send_message(msg.chat.id, "Submissions are currently disabled")
-            .await?;
-        return Ok(());
-    }
-    // Check if the user is part of a team
-    let user_id = msg.from.as_ref().unwrap().id.0 as i64;
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 LIMIT 1")
-        .bind(user_id)
-        .fetch_optional(&pool)
-        .await?;
-    if user.is_none() {
-        bot.send_message(
-            msg.chat.id,
-            "You are not part of a team. Use /join_team to join a team.",
-        )
-        .await?;
-        return Ok(());
-    }
+/// Insert or replace an entry in the `config` table
+async fn set_config_value(pool: &SqlitePool, name: &str, value: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO config (name, value) VALUES ($1, $2)
+        ON CONFLICT(name) DO UPDATE SET value = excluded.value",
+    )
+    .bind(name)
+    .bind(value)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
 
-    let file_id = match media.clone() {
-        Media::Photo(photos) => {
-            let img = photos.photo.last().expect("Didn't receive any photo(s)");
-            let file_id = &img.file; // Get the file ID of the first photo size
-            bot.get_file(file_id.id.clone()).await?
+/// Organizer-customizable message from the `config` table, falling back to `default`
+async fn config_message(pool: &SqlitePool, name: &str, default: &str) -> String {
+    match config_value(pool, name).await {
+        Ok(Some(message)) if !message.trim().is_empty() => message,
+        Ok(_) => default.to_owned(),
+        Err(e) => {
+            log::error!("Failed to load message {:?}: {}", name, e);
+            default.to_owned()
         }
-        Media::Video(video) => {
-            let file_id = &video.video.file; // Get the file ID of the first photo size
-            bot.get_file(file_id.id.clone()).await?
-        }
-    };
-    let file = bot.get_file(file_id.id.clone()).await?;
+    }
+}
 
-    bot.send_chat_action(msg.chat.id, teloxide::types::ChatAction::UploadPhoto)
+/// Teams ranked by score with their number of photos and videos
+async fn scoreboard_text(pool: &SqlitePool) -> BotResult<String> {
+    let res = sqlx::query_as::<_, TeamScore>(TEAM_SCORES_QUERY)
+        .fetch_all(pool)
         .await?;
+    let media_counts = sqlx::query_as::<_, MediaCount>(
+        "SELECT team, SUM(type = 0) AS photos, SUM(type = 1) AS videos
+                FROM submissions
+                WHERE NOT test
+                GROUP BY team",
+    )
+    .fetch_all(pool)
+    .await?;
+    let media_of = |team: &str| {
+        media_counts
+            .iter()
+            .find(|x| x.team == team)
+            .map(|x| (x.photos, x.videos))
+            .unwrap_or_default()
+    };
+    let points = points_format(pool).await;
+    let emojis = team_emojis(pool).await?;
+    let scores = res
+        .iter()
+        .enumerate()
+        .map(|(place, x)| {
+            let (photos, videos) = media_of(&x.team);
+            let emoji = emojis
+                .get(&x.team)
+                .map(|emoji| format!("{} ", emoji))
+                .unwrap_or_default();
+            format!(
+                "{}. {}`{}` with {} (📷 {} / 🎥 {})",
+                place + 1,
+                emoji,
+                x.team,
+                format_points(x.score, &points),
+                photos,
+                videos
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let (photos, videos) = media_counts
+        .iter()
+        .fold((0, 0), |(p, v), x| (p + x.photos, v + x.videos));
+    Ok(format!(
+        "Scoreboard:\n{}\n\nSubmissions: 📷 {} photo(s) / 🎥 {} video(s)",
+        scores, photos, videos
+    ))
+}
 
-    let path = format!("./submissions/{}", file.path.replace("/", "_"));
-    let mut dst = fs::File::create(path.clone()).await?;
-    bot.download_file(&file.path, &mut dst).await?;
-    log::info!(
-        "Received photo from {:?}",
-        msg.from.as_ref().unwrap().full_name()
-    );
-    log::info!("Photo downloaded: {:?} to `{:?}`", file, path);
+/// Message id of the scoreboard pinned in the judge chat with /pin_scoreboard
+async fn pinned_scoreboard(pool: &SqlitePool) -> Result<Option<MessageId>, sqlx::Error> {
+    Ok(config_value(pool, "pinned_scoreboard_message_id")
+        .await?
+        .and_then(|x| x.parse().ok())
+        .map(MessageId))
+}
 
-    // TODO: This should be retrieved from the database
-    // TODO: Team name needs to be taken from databse
-    let sub = Submission {
-        message_id: msg.id.0 as i64,
-        team: "".to_string(),
-        date: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
-        caption: msg.caption().unwrap_or_default().to_string(),
-        r#type: match media {
-            Media::Photo(_) => 0,
-            Media::Video(_) => 1,
-        },
-        user: msg.from.clone().unwrap().id.0 as i64,
-    };
-    let result = sqlx::query(
-        "INSERT INTO submissions (message_id, team, date, caption, type, user)
-        SELECT $1, team, datetime('now'), $2, $3, $4 FROM users WHERE id = $4", // VALUES ($1, $2, datetime('now'), $3, $4, $5)",
+/// Bring the pinned scoreboard up to date; failures are only logged since scores are
+/// already saved at this point
+async fn refresh_pinned_scoreboard(bot: &Bot, pool: &SqlitePool, judge_chat: ChatId) {
+    let result = async {
+        let Some(message_id) = pinned_scoreboard(pool).await? else {
+            return Ok(());
+        };
+        let text = scoreboard_text(pool).await?;
+        match bot.edit_message_text(judge_chat, message_id, text).await {
+            Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => Ok(()),
+            Err(e) => Err(BotError::from(e)),
+        }
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("Could not update the pinned scoreboard: {}", e);
+    }
+}
+
+/// Format of displayed points from `points_format` in the `config` table: either a unit
+/// appended to the number ("pts.", "points", "⭐") or a template with `{}` for the number
+async fn points_format(pool: &SqlitePool) -> String {
+    config_message(pool, "points_format", "pts.").await
+}
+
+fn format_points(points: i64, format: &str) -> String {
+    render_points(&points.to_string(), format)
+}
+
+/// Like [`format_points`] but always signed, for points added to or removed from a score
+fn format_points_change(points: i64, format: &str) -> String {
+    render_points(&format!("{:+}", points), format)
+}
+
+fn render_points(number: &str, format: &str) -> String {
+    if format.contains("{}") {
+        format.replace("{}", number)
+    } else {
+        format!("{} {}", number, format)
+    }
+}
+
+/// Telegram albums hold at most 10 media
+const MEDIA_GROUP_SIZE: usize = 10;
+
+/// Media of a team's submissions whose downloaded file is still on disk, photos as thumbnails
+async fn submission_media(pool: &SqlitePool, team: &str) -> Result<Vec<InputMedia>, sqlx::Error> {
+    let files: Vec<(String, i32, String)> = sqlx::query_as(
+        "SELECT COALESCE(thumbnail_path, file_path) AS path, type, caption FROM submissions
+        WHERE team = $1 AND path IS NOT NULL
+        ORDER BY date",
     )
-    // TODO: Move to optional fields without setting them to ""
-    .bind(sub.message_id)
-    // .bind(sub.team)
-    .bind(sub.caption)
-    .bind(sub.r#type)
-    .bind(sub.user)
-    .execute(&pool)
+    .bind(team)
+    .fetch_all(pool)
     .await?;
-    log::trace!("SQL Result {:?}", result);
+    Ok(files
+        .into_iter()
+        .filter(|(path, _, _)| Path::new(path).exists())
+        .map(|(path, media_type, caption)| {
+            let file = InputFile::file(Path::new(&path));
+            match media_type {
+                1 => InputMedia::Video(InputMediaVideo::new(file).caption(caption)),
+                _ => InputMedia::Photo(InputMediaPhoto::new(file).caption(caption)),
+            }
+        })
+        .collect())
+}
 
-    // Join the tables users and submissions on the user id
-    let sub_ext = sqlx::query_as::<_, SubmissionExtended>(
-        "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, f.id AS forum_id
-        FROM submissions s
-        LEFT JOIN users u ON s.user = u.id
-        LEFT JOIN forums f ON s.team = f.name
-        WHERE s.message_id = $1
-        LIMIT 1",
-    ).bind(msg.id.0).fetch_one(&pool).await?;
-    log::warn!("{:?}", sub_ext);
-    if let None = sub_ext.forum_id {
-        log::warn!("Did not find associated forum; will create");
+/// Send `media` as albums. Uploaded files are replaced by their Telegram file id, so sending
+/// the same media again doesn't upload it a second time.
+async fn send_gallery(
+    bot: &Bot,
+    chat_id: Recipient,
+    thread_id: Option<ThreadId>,
+    media: &mut [InputMedia],
+) -> Result<(), teloxide::RequestError> {
+    for chunk in media.chunks_mut(MEDIA_GROUP_SIZE) {
+        // Albums need at least two media
+        let sent = match chunk {
+            [InputMedia::Photo(photo)] => {
+                let mut request = bot
+                    .send_photo(chat_id.clone(), photo.media.clone())
+                    .caption(photo.caption.clone().unwrap_or_default());
+                request.message_thread_id = thread_id;
+                vec![request.await?]
+            }
+            [InputMedia::Video(video)] => {
+                let mut request = bot
+                    .send_video(chat_id.clone(), video.media.clone())
+                    .caption(video.caption.clone().unwrap_or_default());
+                request.message_thread_id = thread_id;
+                vec![request.await?]
+            }
+            _ => {
+                let mut request = bot.send_media_group(chat_id.clone(), chunk.to_vec());
+                request.message_thread_id = thread_id;
+                request.await?
+            }
+        };
+        for (item, message) in chunk.iter_mut().zip(sent) {
+            match item {
+                InputMedia::Photo(photo) => {
+                    if let Some(size) = message.photo().and_then(|sizes| sizes.last()) {
+                        photo.media = InputFile::file_id(size.file.id.clone());
+                    }
+                }
+                InputMedia::Video(video) => {
+                    if let Some(file) = message.video() {
+                        video.media = InputFile::file_id(file.file.id.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
     }
+    Ok(())
+}
 
-    // Forward to judge chat
-    let mut forwarded_msg = bot.forward_message(cfg.judge_chat, msg.chat.id, msg.id);
-    if let Some(thread_id) = sub_ext.forum_id {
-        log::debug!("Forwarding to forum {:?}", thread_id);
-        forwarded_msg = forwarded_msg.message_thread_id(ThreadId(MessageId(thread_id)));
+/// Telegram rejects messages longer than 4096 characters; leave some headroom
+const MAX_MESSAGE_LENGTH: usize = 4000;
+
+/// Split `text` at line breaks into chunks that each fit into a single message
+fn split_message(text: &str) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    for line in text.lines() {
+        if !current.is_empty()
+            && current.chars().count() + line.chars().count() + 1 > MAX_MESSAGE_LENGTH
+        {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        // Hard-wrap single lines which are too long on their own
+        let mut line = line;
+        while line.chars().count() > MAX_MESSAGE_LENGTH {
+            let (idx, _) = line.char_indices().nth(MAX_MESSAGE_LENGTH).unwrap();
+            chunks.push(line[..idx].to_owned());
+            line = &line[idx..];
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Emojis the teams picked with /team_emoji
+async fn team_emojis(pool: &SqlitePool) -> Result<HashMap<String, String>, sqlx::Error> {
+    let emojis: Vec<(String, String)> =
+        sqlx::query_as("SELECT name, emoji FROM teams WHERE emoji IS NOT NULL")
+            .fetch_all(pool)
+            .await?;
+    Ok(emojis.into_iter().collect())
+}
+
+/// Icon colors Telegram allows for forum topics
+const TOPIC_ICON_COLORS: [u32; 6] = [0x6FB9F0, 0xFFD67E, 0xCB86DB, 0x8EEE98, 0xFF93B2, 0xFB6F5F];
+
+/// Stable hash of a team name (FNV-1a), unlike `DefaultHasher` it doesn't change between
+/// releases
+fn team_hash(team: &str) -> u32 {
+    team.bytes().fold(0x811C9DC5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// Topic icon color of a team, derived from the name so it doesn't depend on the order the
+/// topics are created in
+fn topic_icon_color(team: &str) -> u32 {
+    TOPIC_ICON_COLORS[team_hash(team) as usize % TOPIC_ICON_COLORS.len()]
+}
+
+/// Team name prefixed with the team's emoji, if any
+fn team_display(team: &str, emojis: &HashMap<String, String>) -> String {
+    match emojis.get(team) {
+        Some(emoji) => format!("{} {}", emoji, team),
+        None => team.to_owned(),
     }
-    let forwarded_msg = forwarded_msg.await?;
+}
 
-    bot.send_message(cfg.judge_chat, submission_message(&sub_ext))
-        .reply_parameters(ReplyParameters::new(forwarded_msg.id))
-        .disable_notification(true)
-        .await?;
+/// Change to the forums table after a successful topic update
+enum ForumWrite {
+    Create {
+        id: i32,
+        name: String,
+        title: String,
+        icon_color: u32,
+    },
+    SetOpen {
+        id: i32,
+        open: bool,
+    },
+    Rename {
+        id: i32,
+        title: String,
+    },
+}
+
+impl ForumWrite {
+    async fn store(self, pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        match self {
+            ForumWrite::Create {
+                id,
+                name,
+                title,
+                icon_color,
+            } => {
+                sqlx::query(
+                    "INSERT INTO forums (id, name, title, icon_color) VALUES ($1, $2, $3, $4)",
+                )
+                .bind(id)
+                .bind(name)
+                .bind(title)
+                .bind(icon_color)
+                .execute(pool)
+                .await?;
+            }
+            ForumWrite::SetOpen { id, open } => {
+                sqlx::query("UPDATE forums SET open = $1 WHERE id = $2")
+                    .bind(open)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+            ForumWrite::Rename { id, title } => {
+                sqlx::query("UPDATE forums SET title = $1 WHERE id = $2")
+                    .bind(title)
+                    .bind(id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
 
-    // Select challenges from the table challenges that have not yet been completed by the team of user with user id = sub.user
-    let remaining_challenges = sqlx::query_as::<_, Challenge>(
-        "SELECT name, short_name
-        FROM challenges
-        WHERE name NOT IN (
-            SELECT challenge_name
-            FROM judgement j
-            LEFT JOIN submissions s ON j.submission_id = s.message_id
-            WHERE s.team = (
-                SELECT team
-                FROM users
-                WHERE id = $1))",
+/// Whether /close_all_forums closed the topics for the end of the event
+async fn forums_closed(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    Ok(config_value(pool, "forums_closed")
+        .await?
+        .and_then(|x| x.parse::<bool>().ok())
+        .unwrap_or_default())
+}
+
+/// Create, reopen, rename and close team topics to match the teams in the database.
+///
+/// `lock` is held for the whole run: the topics to create are derived from the forums table
+/// before it is written, so two concurrent runs (e.g. two members of a new team joining at
+/// the same time) would both create a topic for the team.
+async fn update_teams_in_forum(
+    actions: &impl BotActions,
+    pool: &SqlitePool,
+    lock: &Mutex<()>,
+    forum_chat: &Recipient,
+) -> BotResult {
+    let _guard = lock.lock().await;
+    // After /close_all_forums the topics stay closed until the next /update_team_forums
+    if forums_closed(pool).await? {
+        log::info!("Forum topics are closed for the end of the event, not updating them");
+        return Ok(());
+    }
+    let teams: HashSet<_> =
+        sqlx::query_as::<_, Team>("SELECT team, COUNT(*) AS count FROM users GROUP BY team")
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|x| x.team.clone())
+            .collect();
+    let teams_in_forum = sqlx::query_as::<_, Forum>(
+        "SELECT DISTINCT id, name, open, title, closed_manually FROM forums",
     )
-    .bind(sub.user)
-    .fetch_all(&pool)
+    .fetch_all(pool)
     .await?;
+    let emojis = team_emojis(pool).await?;
+    let submission_counts: HashMap<String, i64> =
+        sqlx::query_as("SELECT team, COUNT(*) FROM submissions WHERE test = 0 GROUP BY team")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+    let topic_title = |team: &str| {
+        format!(
+            "{} ({})",
+            team_display(team, &emojis),
+            submission_counts.get(team).copied().unwrap_or_default()
+        )
+    };
 
-    let keyboard = make_keyboard(
-        msg.from.unwrap().id.0.to_string(),
-        msg.id.0.to_string(),
-        remaining_challenges,
+    let forum_team_names: HashSet<_> = teams_in_forum
+        .clone()
+        .iter()
+        .map(|x| x.name.to_owned())
+        .collect();
+    let forums_to_create: HashSet<_> = teams
+        .clone()
+        .into_iter()
+        .filter(|team| !forum_team_names.contains(team))
+        .collect();
+    // Teams that reform under a previous name get their closed topic back, unless an
+    // organizer closed it with /close_forum
+    let forums_to_reopen = teams_in_forum
+        .iter()
+        .filter(|forum| !forum.open && !forum.closed_manually && teams.contains(&forum.name))
+        .cloned()
+        .collect::<HashSet<Forum>>();
+    // Topic titles follow changes of the team emoji and the submission count. The stored
+    // title is the last one shown, so unchanged topics aren't edited.
+    let forums_to_rename = teams_in_forum
+        .iter()
+        .filter(|forum| forum.open && !forum.closed_manually && teams.contains(&forum.name))
+        .filter(|forum| forum.title.as_deref().unwrap_or(&forum.name) != topic_title(&forum.name))
+        .cloned()
+        .collect::<HashSet<Forum>>();
+    let forums_to_close = teams_in_forum
+        .into_iter()
+        .filter(|forum| forum.open && !teams.contains(&forum.name))
+        .collect::<HashSet<Forum>>();
+
+    // The Telegram calls run concurrently. Each one's bookkeeping is written as soon as it
+    // succeeds, so a created topic isn't forgotten when another call or write fails.
+    let new_teams_futures = forums_to_create.iter().map(|team| async {
+        let title = topic_title(team);
+        let icon_color = topic_icon_color(team);
+        let thread_id = actions
+            .create_topic(forum_chat.clone(), title.clone(), icon_color)
+            .await?;
+        log::warn!("Created {:?} in {:?}", team.to_owned(), thread_id);
+        ForumWrite::Create {
+            id: thread_id.0 .0,
+            name: team.to_owned(),
+            title,
+            icon_color,
+        }
+        .store(pool)
+        .await?;
+        BotResult::Ok(())
+    });
+    let reopen_forum_topics_futures = forums_to_reopen.iter().map(|thread| async {
+        actions
+            .reopen_topic(forum_chat.clone(), ThreadId(MessageId(thread.id)))
+            .await?;
+        log::warn!("Reopened topic {:?}", thread.to_owned());
+        ForumWrite::SetOpen {
+            id: thread.id,
+            open: true,
+        }
+        .store(pool)
+        .await?;
+        BotResult::Ok(())
+    });
+    let rename_forum_topics_futures = forums_to_rename.iter().map(|thread| async {
+        let title = topic_title(&thread.name);
+        actions
+            .rename_topic(
+                forum_chat.clone(),
+                ThreadId(MessageId(thread.id)),
+                title.clone(),
+            )
+            .await?;
+        log::warn!("Renamed topic {:?}", thread.to_owned());
+        ForumWrite::Rename {
+            id: thread.id,
+            title,
+        }
+        .store(pool)
+        .await?;
+        BotResult::Ok(())
+    });
+    let close_forum_topics_futures = forums_to_close.iter().map(|thread| async {
+        actions
+            .close_topic(forum_chat.clone(), ThreadId(MessageId(thread.id)))
+            .await?;
+        log::warn!("Closed topic {:?}", thread.to_owned());
+        ForumWrite::SetOpen {
+            id: thread.id,
+            open: false,
+        }
+        .store(pool)
+        .await?;
+        BotResult::Ok(())
+    });
+
+    let (created, reopened, renamed, closed) = futures::join!(
+        futures::future::join_all(new_teams_futures),
+        futures::future::join_all(reopen_forum_topics_futures),
+        futures::future::join_all(rename_forum_topics_futures),
+        futures::future::join_all(close_forum_topics_futures),
     );
-    let mut response = bot
-        .send_message(cfg.judge_chat, "Select challenge or action")
-        .reply_markup(keyboard)
-        .disable_notification(true);
-    if let Some(thread_id) = sub_ext.forum_id {
-        response = response.message_thread_id(ThreadId(MessageId(thread_id)));
+    // Failed calls are retried by the next reconciliation
+    for result in created
+        .into_iter()
+        .chain(reopened)
+        .chain(renamed)
+        .chain(closed)
+    {
+        if let Err(e) = result {
+            log::error!("Failed to update forum topic: {}", e);
+        }
     }
-    response.await?;
 
     Ok(())
 }
 
-async fn maintainer_commands(
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Media {
+    Photo(MediaPhoto),
+    Video(MediaVideo),
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn receive_submission(
+    media: Media,
     msg: Message,
-    bot: Bot,
-    cmd: MaintainerCommands,
+    actions: Actions,
+    cfg: ConfigParameters,
     pool: SqlitePool,
-    lock: Arc<Mutex<()>>,
     submissions_enabled: Arc<AtomicBool>,
-    cfg: ConfigParameters,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    match cmd {
-        MaintainerCommands::ListTeams => {
-            let res =
-                sqlx::query_as::<_, Team>("SELECT DISTINCT team, COUNT(*) as count FROM users")
-                    .fetch_all(&pool)
-                    .await
-                    .unwrap();
-            let teams = res
-                .into_iter()
-                .map(|x| format!("- {} (#{})", x.team, x.count))
-                .collect::<Vec<String>>()
-                .join("\n");
+    queue: JudgeQueue,
+    downloads: DownloadPermits,
+) -> BotResult {
+    if !submissions_enabled.load(Ordering::Relaxed) {
+        let text = config_message(
+            &pool,
+            "submissions_disabled_message",
+            "Submissions are currently disabled",
+        )
+        .await;
+        actions
+            .send_text(msg.chat.id, text, SendOptions::default())
+            .await?;
+        return Ok(());
+    }
+    let now = chrono::Utc::now().naive_utc();
+    if event_start(&pool).await?.is_some_and(|start| now < start) {
+        actions
+            .send_text(
+                msg.chat.id,
+                "The hunt hasn't started yet".to_owned(),
+                SendOptions::default(),
+            )
+            .await?;
+        return Ok(());
+    }
+    if submission_deadline(&pool)
+        .await?
+        .is_some_and(|deadline| now >= deadline)
+    {
+        actions
+            .send_text(
+                msg.chat.id,
+                "Submissions are closed, the hunt is over".to_owned(),
+                SendOptions::default(),
+            )
+            .await?;
+        return Ok(());
+    }
+    // Check if the user is part of a team
+    let user_id = sender(&msg)?.id.0 as i64;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1 LIMIT 1")
+        .bind(user_id)
+        .fetch_optional(&pool)
+        .await?;
+    let Some(user) = user else {
+        let text = config_message(
+            &pool,
+            "no_team_message",
+            "You are not part of a team. Use /join_team to join a team.",
+        )
+        .await;
+        actions
+            .send_text(msg.chat.id, text, SendOptions::default())
+            .await?;
+        return Ok(());
+    };
 
-            bot.send_message(msg.chat.id, format!("Teams:\n{}", teams))
+    // Limit per person (not per team), so one member can't carry the whole team
+    let limit = config_value(&pool, "max_submissions_per_user")
+        .await?
+        .and_then(|x| x.parse::<i64>().ok());
+    if let Some(limit) = limit {
+        let submitted: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM submissions WHERE user = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await?;
+        if submitted >= limit {
+            let text = config_message(
+                &pool,
+                "personal_submission_limit_message",
+                "You've reached your personal submission limit",
+            )
+            .await;
+            actions
+                .send_text(msg.chat.id, text, SendOptions::default())
                 .await?;
-            Ok(())
+            return Ok(());
         }
-        MaintainerCommands::ListTeamMembers => {
-            let res = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY team")
-                .fetch_all(&pool)
-                .await
-                .unwrap();
-            let users = res
-                .iter()
-                .map(|x| format!("- {} (#{}) -> {}", x.to_string(), x.id, x.team))
-                .collect::<Vec<String>>()
-                .join("\n");
+    }
 
-            bot.send_message(msg.chat.id, format!("Participants:\n{}", users))
-                .await?;
-            Ok(())
-        }
-        MaintainerCommands::Scoreboard => {
-            // List teams and their scores
-            let res = sqlx::query_as::<_, TeamScore>(
-                "SELECT s.team, SUM(j.points) as score
-                FROM judgement j
-                LEFT JOIN submissions s ON j.submission_id = s.message_id
-                LEFT JOIN users u ON s.team = u.team
-                WHERE j.valid = 1
-                GROUP BY s.team ORDER BY score DESC",
-            )
-            .fetch_all(&pool)
-            .await?;
-            let scores = res
-                .iter()
-                .enumerate()
-                .map(|(place, x)| format!("{}. `{}` with {} pts.", place + 1, x.team, x.score))
-                .collect::<Vec<String>>()
-                .join("\n");
-            bot.send_message(msg.chat.id, format!("Scoreboard:\n{}", scores))
+    // Daily limit per team to pace the event
+    let daily_limit = config_value(&pool, "max_team_submissions_per_day")
+        .await?
+        .and_then(|x| x.parse::<i64>().ok());
+    if let Some(daily_limit) = daily_limit {
+        let rollover_hour = config_value(&pool, "daily_limit_reset_hour")
+            .await?
+            .and_then(|x| x.parse::<u32>().ok())
+            .filter(|hour| *hour < 24)
+            .unwrap_or(calendar::DEFAULT_ROLLOVER_HOUR);
+        let submitted_today: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM submissions WHERE team = $1 AND date >= $2 AND date < $3 AND NOT test",
+        )
+        .bind(&user.team)
+        .bind(
+            calendar::day_start(now, rollover_hour)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        )
+        .bind(
+            calendar::next_day_start(now, rollover_hour)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        )
+        .fetch_one(&pool)
+        .await?;
+        if submitted_today >= daily_limit {
+            actions
+                .send_text(
+                    msg.chat.id,
+                    format!(
+                        "Your team has reached its limit of {} submission(s) for today. The limit resets at {:02}:00 UTC.",
+                        daily_limit, rollover_hour
+                    ),
+                    SendOptions::default(),
+                )
                 .await?;
-            Ok(())
+            return Ok(());
         }
-        MaintainerCommands::ListTeamSubmissions => {
-            let res = sqlx::query_as::<_, TeamScore>(
-                "SELECT s.team, SUM(j.points) as score
-                FROM judgement j
-                LEFT JOIN submissions s ON j.submission_id = s.message_id
-                LEFT JOIN users u ON s.team = u.team
-                WHERE j.valid = 1
-                GROUP BY s.team ORDER BY score DESC",
-            )
-            .fetch_all(&pool)
-            .await?;
-            for team in res {
-                let submissions = sqlx::query_as::<_, SubmissionExtended>(
-                    "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id
-                    FROM submissions s
-                    LEFT JOIN users u ON s.user = u.id
-                    WHERE s.team = $1",
-                )
-                .bind(team.clone().team)
-                .fetch_all(&pool)
-                .await?;
-                let submissions = submissions
-                    .iter()
-                    .map(|x| submission_message(x))
-                    .collect::<Vec<String>>()
-                    .join("\n\n");
-                bot.send_message(
-                    msg.chat.id,
-                    format!("Submissions for team `{}`:\n{}", team.team, submissions),
-                )
-                .await?;
-            }
-            Ok(())
-        }
-        MaintainerCommands::ListTeamSubmissionJudgments => {
-            let res = sqlx::query_as::<_, TeamScore>(
-                "SELECT s.team, SUM(j.points) as score
-                FROM judgement j
-                LEFT JOIN submissions s ON j.submission_id = s.message_id
-                LEFT JOIN users u ON s.team = u.team
-                WHERE j.valid = 1
-                GROUP BY s.team ORDER BY score DESC",
-            )
-            .fetch_all(&pool)
-            .await?;
-            for team in res {
-                let judgements = sqlx::query_as::<_, Judgement>(
-                    "SELECT j.submission_id, j.challenge_name, j.points, j.valid
-                    FROM judgement j
-                    LEFT JOIN submissions s ON j.submission_id = s.message_id
-                    WHERE s.team = $1",
-                )
-                .bind(team.clone().team)
-                .fetch_all(&pool)
-                .await?;
-                let judgements = judgements
-                    .iter()
-                    .map(|x| {
-                        format!(
-                            "- ref=`{}` challenge=`{}` pts={} valid={}",
-                            x.submission_id, x.challenge_name, x.points, x.valid
-                        )
-                    })
-                    .collect::<Vec<String>>()
-                    .join("\n");
-                bot.send_message(
+    }
+
+    // Reject videos of the wrong length before downloading them
+    let duration = match &media {
+        Media::Photo(_) => None,
+        Media::Video(video) => Some(video.video.duration.seconds()),
+    };
+    if let Some(duration) = duration {
+        let min = config_value(&pool, "min_video_duration_secs")
+            .await?
+            .and_then(|x| x.parse::<u32>().ok());
+        let max = config_value(&pool, "max_video_duration_secs")
+            .await?
+            .and_then(|x| x.parse::<u32>().ok());
+        let problem = match (min, max) {
+            (Some(min), _) if duration < min => Some(format!(
+                "Your video is too short ({}s), videos need to be at least {}s long",
+                duration, min
+            )),
+            (_, Some(max)) if duration > max => Some(format!(
+                "Your video is too long ({}s), please keep it to {}s or less",
+                duration, max
+            )),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            actions
+                .send_text(
                     msg.chat.id,
-                    format!("Judgements for team `{}`:\n{}", team.team, judgements),
+                    problem,
+                    SendOptions {
+                        reply_to: Some(msg.id),
+                        ..Default::default()
+                    },
                 )
                 .await?;
-            }
-            Ok(())
-        }
-        MaintainerCommands::UpdateTeamForums => {
-            let _guard = lock.lock().await;
-            update_teams_in_forum(&bot, &pool).await?;
-            Ok(())
-        }
-        MaintainerCommands::EnableSubmissions { status } => {
-            submissions_enabled.store(status, Ordering::Relaxed);
-            Ok(())
+            return Ok(());
         }
-        MaintainerCommands::ListParticipants => {
-            let users = sqlx::query_as::<_, User>("SELECT * FROM users")
-                .fetch_all(&pool)
-                .await
-                .unwrap();
-            let users = users
-                .iter()
-                .map(|x| format!("- {} (#{})", x.to_string(), x.id))
-                .collect::<Vec<String>>()
-                .join("\n");
+    }
 
-            bot.send_message(msg.chat.id, format!("Participants:\n{}", users))
-                .await?;
-            Ok(())
+    let file = match media.clone() {
+        Media::Photo(photos) => {
+            let img = photos.photo.last().expect("Didn't receive any photo(s)");
+            img.file.clone() // The last photo size is the largest one
         }
-        MaintainerCommands::MessageToParticipants(message) => {
-            if message.is_empty() {
-                bot.send_message(msg.chat.id, "Broadcast error: Empty message")
-                    .await?;
-                return Ok(());
-            }
-            // Query over all users and send a message to each of them
-            let users = sqlx::query_as::<_, User>("SELECT * FROM users")
-                .fetch_all(&pool)
+        Media::Video(video) => video.video.file,
+    };
+    let extension = match media {
+        Media::Photo(_) => "jpg",
+        Media::Video(_) => "mp4",
+    };
+
+    // Team and message id keep files of different teams and events apart when browsing
+    let path = cfg
+        .submissions_dir
+        .join(format!(
+            "{}_{}_{}.{}",
+            file_name_part(&user.team),
+            msg.id.0,
+            file_name_part(&file.unique_id),
+            extension
+        ))
+        .to_string_lossy()
+        .to_string();
+    actions
+        .chat_action(msg.chat.id, teloxide::types::ChatAction::UploadPhoto)
+        .await?;
+    let downloaded = {
+        let _permit = downloads.acquire(msg.id).await;
+        actions.download(file.id.clone(), Path::new(&path)).await
+    };
+    if let Err(e) = downloaded {
+        log::error!("Download of {:?} from {} failed: {}", file, user_id, e);
+        return report_unprocessable_file(&actions, msg.chat.id).await;
+    }
+    log::info!("Received photo from {:?}", sender(&msg)?.full_name());
+    log::info!("Photo downloaded: {:?} to `{:?}`", file, path);
+
+    // Anti-cheat: photos taken before the event were likely staged in advance
+    let taken_at = match media {
+        Media::Photo(_) => {
+            let path = PathBuf::from(&path);
+            tokio::task::spawn_blocking(move || metadata::capture_time(&path))
                 .await
-                .unwrap();
-            for user in users {
-                if cfg.maintainers.contains(&UserId(user.id as u64)) {
-                    if msg.from.as_ref().unwrap().id.0 == user.id as u64 {
-                        continue;
-                    } else {
-                        bot.send_message(
-                            UserId(user.id as u64),
-                            format!("Broadcast from {}", msg.from.as_ref().unwrap().full_name()),
-                        )
-                        .await?;
-                    }
-                }
-                bot.send_message(UserId(user.id as u64), message.clone())
-                    .await?;
-            }
-            bot.send_message(msg.chat.id, "Message sent").await?;
-            Ok(())
+                .ok()
+                .flatten()
         }
-        MaintainerCommands::Judge {
-            image_ref: submission_ref,
-            challenge,
-        } => {
-            // Retrieve the associate aka user who submitted the submission from the sql
-            let associate = sqlx::query_as::<_, User>(
-                "SELECT u.id, u.team, u.username, u.first_name, u.last_name
-                FROM submissions s
-                LEFT JOIN users u ON s.user = u.id
-                WHERE s.message_id = $1",
-            )
-            .bind(submission_ref)
-            .fetch_optional(&pool)
-            .await?;
-            // Check that challenge exists
-            let challenge = match challenge.as_str() {
-                // TODO: Handle this in a better way
-                "___unclear" => Some(Challenge {
-                    name: "___unclear".to_owned(),
-                    short_name: "Unclear".to_owned(),
-                }),
-                "___invalid" => Some(Challenge {
-                    name: "___invalid".to_owned(),
-                    short_name: "Invalid".to_owned(),
-                }),
-                _ => {
-                    sqlx::query_as::<_, Challenge>(
-                        "SELECT name, short_name
-                FROM challenges
-                WHERE name = $1",
-                    )
-                    .bind(challenge)
-                    .fetch_optional(&pool)
-                    .await?
-                }
-            };
-            match (associate, challenge) {
-                (Some(user), Some(challenge)) => {
-                    judge(
-                        user.id.to_string(),
-                        submission_ref.to_string(),
-                        challenge.name,
-                        &bot,
-                        &pool,
-                    )
-                    .await?;
+        Media::Video(_) => None,
+    };
+    let event_start = event_start(&pool).await?;
+    let taken_before_event = taken_at
+        .zip(event_start)
+        .filter(|(taken_at, event_start)| taken_at < event_start)
+        .map(|(taken_at, _)| taken_at);
 
-                    bot.send_message(msg.chat.id, "Submission successfully judged")
-                        .await?;
-                }
-                (_, None) => {
-                    bot.send_message(msg.chat.id, "Challenge not found").await?;
+    // Thumbnails save disk space, the full size photo can be dropped once one exists
+    let thumbnail_path = match media {
+        Media::Photo(_) => {
+            let source = PathBuf::from(&path);
+            match tokio::task::spawn_blocking(move || thumbnail::create_thumbnail(&source)).await {
+                Ok(Ok(thumbnail)) => Some(thumbnail.to_string_lossy().to_string()),
+                Ok(Err(e)) => {
+                    log::warn!("Could not create a thumbnail of {:?}: {}", path, e);
+                    None
                 }
-                (None, _) => {
-                    bot.send_message(msg.chat.id, "Submission not found")
-                        .await?;
+                Err(e) => {
+                    log::warn!("Thumbnail task for {:?} failed: {}", path, e);
+                    None
                 }
             }
-
-            Ok(())
         }
-        MaintainerCommands::ListSubmissions => {
-            let submissions = sqlx::query_as::<_, SubmissionExtended>("  
-                SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id
-                FROM submissions s
-                LEFT JOIN users u ON s.user = u.id").fetch_all(&pool).await?;
-            let submissions = submissions
-                .iter()
-                .map(|x| submission_message(x))
-                .collect::<Vec<String>>()
-                .join("\n");
-            bot.send_message(msg.chat.id, format!("Submissions: {}", submissions))
-                .await?;
-            Ok(())
+        Media::Video(_) => None,
+    };
+    // Placeholder for galleries, the thumbnail is quicker to decode if there is one
+    let blurhash = match media {
+        Media::Photo(_) => {
+            let source = PathBuf::from(thumbnail_path.as_ref().unwrap_or(&path));
+            match tokio::task::spawn_blocking(move || thumbnail::blurhash(&source)).await {
+                Ok(Ok(blurhash)) => Some(blurhash),
+                Ok(Err(e)) => {
+                    log::warn!("Could not compute the blurhash of {:?}: {}", path, e);
+                    None
+                }
+                Err(e) => {
+                    log::warn!("Blurhash task for {:?} failed: {}", path, e);
+                    None
+                }
+            }
         }
-        MaintainerCommands::ListJudgements => {
-            let judgements = sqlx::query_as::<_, Judgement>("SELECT * FROM judgement")
-                .fetch_all(&pool)
-                .await?;
-            let judgements = judgements
-                .iter()
-                .map(|x| {
-                    format!(
-                        "- ref=`{}` challenge=`{}` pts={} valid={}",
-                        x.submission_id, x.challenge_name, x.points, x.valid
-                    )
-                })
-                .collect::<Vec<String>>()
-                .join("\n");
-            bot.send_message(msg.chat.id, format!("Judgements: {}", judgements))
-                .await?;
-            Ok(())
+        Media::Video(_) => None,
+    };
+    let keep_full_size = config_value(&pool, "keep_full_size_photos")
+        .await?
+        .and_then(|x| x.parse::<bool>().ok())
+        .unwrap_or(true);
+    let file_path = if thumbnail_path.is_some() && !keep_full_size {
+        if let Err(e) = fs::remove_file(&path).await {
+            log::warn!("Could not remove {:?}: {}", path, e);
         }
+        None
+    } else {
+        Some(path.clone())
+    };
+
+    let (caption, truncated) = truncate_caption(
+        msg.caption().unwrap_or_default(),
+        max_caption_length(&pool).await?,
+    );
+
+    // The team at the time of submission, later team changes don't move it
+    let sub = Submission {
+        message_id: msg.id.0 as i64,
+        team: user.team.clone(),
+        // Time of arrival, the same one the event start and deadline were checked against.
+        // Same format as SQLite's datetime('now'), which older rows were stored with.
+        date: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        caption,
+        r#type: match media {
+            Media::Photo(_) => 0,
+            Media::Video(_) => 1,
+        },
+        user: user.id,
+    };
+    let result = sqlx::query(
+        "INSERT INTO submissions (message_id, team, date, caption, type, user, file_path, taken_at, thumbnail_path, duration, blurhash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+    )
+    .bind(sub.message_id)
+    .bind(sub.team)
+    .bind(sub.date)
+    .bind(sub.caption)
+    .bind(sub.r#type)
+    .bind(sub.user)
+    .bind(file_path)
+    .bind(taken_at.map(|x| x.format("%Y-%m-%d %H:%M:%S").to_string()))
+    .bind(thumbnail_path)
+    .bind(duration)
+    .bind(blurhash)
+    .execute(&pool)
+    .await?;
+    log::trace!("SQL Result {:?}", result);
+
+    // The judge chat is paced, let the participant know it arrived in the meantime
+    if let Err(e) = actions
+        .react(msg.chat.id, msg.id, Some("👀".to_owned()))
+        .await
+    {
+        log::warn!("Could not acknowledge submission {}: {}", msg.id, e);
     }
+    let note = submission_note(taken_before_event, truncated);
+    queue
+        .push(
+            QueuedSubmission {
+                chat_id: msg.chat.id,
+                message_id: msg.id,
+                note,
+                new: true,
+            },
+            &actions,
+            &cfg,
+            &pool,
+        )
+        .await
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    pretty_env_logger::init();
-    let db_url: String = env::var("DATABASE_URL").expect("DATABASE_URL not set");
-    let judge_chat: String = env::var("JUDGE_CHAT_ID").expect("JUDGE_CHAT_ID not set");
-    let judge_chat = ChatId(judge_chat.parse::<i64>().unwrap());
+/// Captions are stored with at most this many characters unless configured otherwise
+const DEFAULT_MAX_CAPTION_LENGTH: usize = 500;
 
-    let maintainers: String = env::var("MAINTAINERS").expect("MAINTAINERS not set");
-    let maintainers = maintainers
-        .split(",")
-        .map(|x| x.parse::<u64>().unwrap())
-        .map(UserId)
-        .collect::<HashSet<UserId>>();
+async fn max_caption_length(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    Ok(config_value(pool, "max_caption_length")
+        .await?
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_CAPTION_LENGTH))
+}
 
-    let bot = Bot::from_env();
-    let db = init_db(&db_url)
-        .await
-        .expect("Failed to initialize database");
+/// Cut `caption` to `max` characters, ending in an ellipsis. Also returns whether it was cut.
+fn truncate_caption(caption: &str, max: usize) -> (String, bool) {
+    if caption.chars().count() <= max {
+        return (caption.to_owned(), false);
+    }
+    let mut truncated = caption
+        .chars()
+        .take(max.saturating_sub(1))
+        .collect::<String>();
+    truncated.push('…');
+    (truncated, true)
+}
 
-    let parameters = ConfigParameters {
-        judge_chat: judge_chat,
-        maintainers: maintainers,
-    };
+/// Note for the judges about a new submission, `None` if there is nothing to point out
+fn submission_note(
+    taken_before_event: Option<chrono::NaiveDateTime>,
+    caption_truncated: bool,
+) -> Option<String> {
+    let notes = [
+        taken_before_event.map(|taken_at| format!("⚠️ taken before event ({})", taken_at)),
+        caption_truncated.then(|| "✂️ caption truncated, see the forwarded message".to_owned()),
+    ];
+    let notes = notes.into_iter().flatten().collect::<Vec<String>>();
+    (!notes.is_empty()).then(|| notes.join("\n"))
+}
 
-    let lock = Arc::new(Mutex::new(()));
-    let submissions_enabled = Arc::new(AtomicBool::new(true));
+/// Submission waiting to be sent to the judge chat
+#[derive(Clone, Debug)]
+struct QueuedSubmission {
+    chat_id: ChatId,
+    message_id: MessageId,
+    note: Option<String>,
+    /// Newly received rather than resent after a clarification
+    new: bool,
+}
 
-    let handler = Update::filter_message()
-        .branch(
-            dptree::entry()
-                .filter_command::<ParticipantCommand>()
-                .filter(|msg: Message, cfg: ConfigParameters| {
-                    !(msg.chat.is_group() || msg.chat.is_supergroup())
-                        || msg.chat.id == cfg.judge_chat
-                })
-                .branch(
-                    // Handle join team separately
-                    dptree::filter(|cmd: ParticipantCommand| {
-                        matches!(cmd, ParticipantCommand::JoinTeam(_))
-                    })
-                    .endpoint(join_team),
-                )
-                .branch(
-                    dptree::entry()
-                        .filter_command::<ParticipantCommand>()
-                        .endpoint(participant_commands_handler),
-                ),
+/// Bounds the number of submission files downloaded at the same time, so a burst of videos
+/// doesn't exhaust memory and bandwidth
+#[derive(Clone, Debug)]
+struct DownloadPermits(Arc<tokio::sync::Semaphore>);
+
+impl DownloadPermits {
+    async fn acquire(&self, message_id: MessageId) -> tokio::sync::SemaphorePermit<'_> {
+        if let Ok(permit) = self.0.try_acquire() {
+            return permit;
+        }
+        log::info!(
+            "Download of submission {} waits for one of the other downloads",
+            message_id
+        );
+        self.0
+            .acquire()
+            .await
+            .expect("download semaphore is never closed")
+    }
+}
+
+/// Queue of submissions for the judge chat, worked off by [`forward_submissions`] so that
+/// bursts of submissions don't run into Telegram's rate limit
+#[derive(Clone, Debug)]
+struct JudgeQueue(tokio::sync::mpsc::UnboundedSender<QueuedSubmission>);
+
+impl JudgeQueue {
+    /// Queue `submission`, or deliver it right away if the worker is gone
+    async fn push(
+        &self,
+        submission: QueuedSubmission,
+        actions: &Actions,
+        cfg: &ConfigParameters,
+        pool: &SqlitePool,
+    ) -> BotResult {
+        if let Err(e) = self.0.send(submission) {
+            log::error!("Judge queue closed, delivering directly");
+            return deliver_to_judges(actions, cfg, pool, e.0).await;
+        }
+        Ok(())
+    }
+
+    /// Queue the stored submissions which never reached the judge chat, because the bot
+    /// stopped while they were queued. Returns how many were queued.
+    async fn requeue_unforwarded(&self, pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let submissions: Vec<(i32, i64)> = sqlx::query_as(
+            "SELECT s.message_id, s.user
+            FROM submissions s
+            LEFT JOIN judgement j ON j.submission_id = s.message_id
+            WHERE s.forwarded_message_id IS NULL AND NOT s.excluded AND NOT s.test
+                AND j.submission_id IS NULL
+            ORDER BY s.date, s.message_id",
         )
-        .branch(
-            // Filter a maintainer by a user ID
-            dptree::filter(|cfg: ConfigParameters, msg: Message| {
-                msg.from
-                    .map(|user| cfg.maintainers.contains(&user.id) && msg.chat.is_private())
-                    .unwrap_or_default()
-            })
-            .filter_command::<MaintainerCommands>()
-            .endpoint(maintainer_commands),
+        .fetch_all(pool)
+        .await?;
+        let count = submissions.len();
+        for (message_id, user) in submissions {
+            // Submissions come from private chats, whose id is the participant's user id
+            let queued = self.0.send(QueuedSubmission {
+                chat_id: ChatId(user),
+                message_id: MessageId(message_id),
+                note: None,
+                new: true,
+            });
+            if queued.is_err() {
+                log::error!(
+                    "Judge queue closed, submission {} stays unforwarded",
+                    message_id
+                );
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Deliver queued submissions to the judge chat, at most
+/// [`JUDGE_CHAT_MESSAGES_PER_MINUTE`] messages per minute
+async fn forward_submissions(
+    mut queue: tokio::sync::mpsc::UnboundedReceiver<QueuedSubmission>,
+    actions: Actions,
+    cfg: ConfigParameters,
+    pool: SqlitePool,
+) {
+    let interval = std::time::Duration::from_secs(
+        60 * MESSAGES_PER_SUBMISSION / JUDGE_CHAT_MESSAGES_PER_MINUTE,
+    );
+    let mut next = tokio::time::Instant::now();
+    while let Some(submission) = queue.recv().await {
+        tokio::time::sleep_until(next).await;
+        let message_id = submission.message_id;
+        if let Err(e) = deliver_to_judges(&actions, &cfg, &pool, submission).await {
+            log::error!("Delivery of submission {} failed: {}", message_id, e);
+        }
+        next = tokio::time::Instant::now() + interval;
+    }
+}
+
+/// Remind all participants of each schedule event `schedule_reminder_minutes` before it
+/// starts. Events are marked as reminded before sending, so a restart never reminds twice.
+async fn remind_of_schedule(actions: Actions, pool: SqlitePool) {
+    // Nobody needs a reminder for what happened while the bot was down
+    if let Err(e) =
+        sqlx::query("UPDATE schedule SET reminded = 1 WHERE starts_at <= datetime('now')")
+            .execute(&pool)
+            .await
+    {
+        log::error!("Could not skip past schedule events: {}", e);
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        SCHEDULE_REMINDER_CHECK_INTERVAL_SECS,
+    ));
+    loop {
+        interval.tick().await;
+        if let Err(e) = send_due_reminders(&actions, &pool).await {
+            log::error!("Schedule reminders failed: {}", e);
+        }
+    }
+}
+
+async fn send_due_reminders(actions: &impl BotActions, pool: &SqlitePool) -> BotResult {
+    let lead_time = config_value(pool, "schedule_reminder_minutes")
+        .await?
+        .and_then(|x| x.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_SCHEDULE_REMINDER_MINUTES);
+    let due = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT id, starts_at, title FROM schedule
+        WHERE reminded = 0 AND starts_at <= datetime('now', $1)
+        ORDER BY starts_at",
+    )
+    .bind(format!("+{} minutes", lead_time))
+    .fetch_all(pool)
+    .await?;
+    if due.is_empty() {
+        return Ok(());
+    }
+    let offset = event_utc_offset(pool).await?;
+    let participants: Vec<i64> = sqlx::query_scalar("SELECT id FROM users")
+        .fetch_all(pool)
+        .await?;
+    for (id, starts_at, title) in due {
+        sqlx::query("UPDATE schedule SET reminded = 1 WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        let starts_at = scoring::parse_date(&starts_at)
+            .map(|x| calendar::format_local(x, offset))
+            .unwrap_or(starts_at);
+        let text = format!("⏰ Coming up: {} at {}", title, starts_at);
+        let mut failed = 0;
+        for participant in &participants {
+            let sent = actions
+                .send_text(ChatId(*participant), text.clone(), SendOptions::default())
+                .await;
+            match sent {
+                Ok(_) => {}
+                Err(e) if e.is_unreachable_user() => failed += 1,
+                Err(e) => {
+                    log::warn!("Could not remind {} of {}: {}", participant, title, e);
+                    failed += 1;
+                }
+            }
+        }
+        log::info!(
+            "Reminded {} participant(s) of {}, {} failed",
+            participants.len() - failed,
+            title,
+            failed
+        );
+    }
+    Ok(())
+}
+
+/// Send a submission to the judge chat, a new one is dropped if that fails
+async fn deliver_to_judges(
+    actions: &Actions,
+    cfg: &ConfigParameters,
+    pool: &SqlitePool,
+    submission: QueuedSubmission,
+) -> BotResult {
+    let QueuedSubmission {
+        chat_id,
+        message_id,
+        note,
+        new,
+    } = submission;
+    if ask_judges(actions, cfg, pool, chat_id, message_id, note).await? {
+        return Ok(());
+    }
+    if new {
+        // Without the forward the judges never see it, don't leave it unjudged forever
+        let files: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT file_path, thumbnail_path FROM submissions WHERE message_id = $1",
         )
-        .branch(
-            // Filter a media messages for submission
-            dptree::filter(|cfg: ConfigParameters, msg: Message| {
-                msg.chat.is_private()
-                    && !msg.chat.is_group()
-                    && !msg.chat.is_supergroup()
-                    && msg.chat.id != cfg.judge_chat
-            })
-            .filter_map(|msg: Message| match msg.kind {
-                MessageKind::Common(MessageCommon {
-                    media_kind: MediaKind::Photo(ref photos),
-                    ..
-                }) => Some(Media::Photo(photos.clone())),
-                MessageKind::Common(MessageCommon {
-                    media_kind: MediaKind::Video(ref video),
-                    ..
-                }) => Some(Media::Video(video.clone())),
-                _ => None,
-            })
-            .endpoint(receive_submission),
+        .bind(message_id.0)
+        .fetch_optional(pool)
+        .await?;
+        sqlx::query("DELETE FROM submissions WHERE message_id = $1")
+            .bind(message_id.0)
+            .execute(pool)
+            .await?;
+        for path in files
+            .into_iter()
+            .flat_map(|(file, thumbnail)| [file, thumbnail])
+        {
+            let Some(path) = path else { continue };
+            if let Err(e) = fs::remove_file(&path).await {
+                log::warn!("Could not remove {:?}: {}", path, e);
+            }
+        }
+    }
+    report_unprocessable_file(actions, chat_id).await
+}
+
+/// Forward a stored submission to the judge chat and ask for a verdict. `note` is added to
+/// the details shown to the judges. Returns `false` if the forward failed.
+async fn ask_judges(
+    actions: &Actions,
+    cfg: &ConfigParameters,
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    message_id: MessageId,
+    note: Option<String>,
+) -> BotResult<bool> {
+    // Join the tables users and submissions on the user id
+    let sub_ext = sqlx::query_as::<_, SubmissionExtended>(
+        "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, f.id AS forum_id
+        FROM submissions s
+        LEFT JOIN users u ON s.user = u.id
+        LEFT JOIN forums f ON s.team = f.name
+        WHERE s.message_id = $1
+        LIMIT 1",
+    ).bind(message_id.0).fetch_one(pool).await?;
+    log::warn!("{:?}", sub_ext);
+    if sub_ext.forum_id.is_none() {
+        log::warn!("Did not find associated forum; will create");
+    }
+
+    let judge_chat = cfg.judge_chat_for(&sub_ext.team);
+    // Forum topics only exist in the judge chat if it is the team forum
+    let thread_id = sub_ext
+        .forum_id
+        .filter(|_| cfg.judge_chat_is_forum && judge_chat == cfg.judge_chat);
+
+    let thread_id = thread_id.map(|thread_id| ThreadId(MessageId(thread_id)));
+
+    // Forward to judge chat
+    if let Some(thread_id) = thread_id {
+        log::debug!("Forwarding to forum {:?}", thread_id);
+    }
+    let forwarded_id = match actions
+        .forward(judge_chat, chat_id, message_id, thread_id)
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            log::error!("Forward of submission {} failed: {}", message_id, e);
+            return Ok(false);
+        }
+    };
+    // Remember the forward so judges can reply to it to judge the submission
+    sqlx::query(
+        "UPDATE submissions SET forwarded_message_id = $1, judge_chat_id = $2 WHERE message_id = $3",
+    )
+    .bind(forwarded_id.0)
+    .bind(judge_chat.0)
+    .bind(message_id.0)
+    .execute(pool)
+    .await?;
+
+    let mut judge_text = submission_message(&sub_ext);
+    if let Some(note) = note {
+        judge_text.push('\n');
+        judge_text.push_str(&note);
+    }
+    actions
+        .send_text(
+            judge_chat,
+            judge_text,
+            SendOptions {
+                reply_to: Some(forwarded_id),
+                silent: true,
+                ..Default::default()
+            },
         )
-        .branch(
-            dptree::filter(|msg: Message, cfg: ConfigParameters| msg.chat.id != cfg.judge_chat)
-                .endpoint(|bot: Bot, msg: Message| async move {
-                    if msg.chat.is_group() || msg.chat.is_supergroup() {
-                        bot.send_message(msg.chat.id, "Please use me in a private chat")
-                            .await?;
-                        return Ok(());
-                    }
+        .await?;
 
-                    if let Some(text) = msg.text() {
-                        // Some easter eggs
-                        let response = match text.to_lowercase().as_str() {
-                            t if t.contains("beer") || t.contains("bier") => {
-                                "I love Bavarian beer!"
-                            }
-                            t if t.contains("prost") => "Prost!",
-                            t if t.contains("servus")
-                                || t.contains("hallo")
-                                || t.contains("hi")
-                                || t.contains("hey") =>
-                            {
-                                "Servus!"
-                            }
-                            _ => "Sorry, I didn't understand your message. /help",
-                        };
-                        bot.send_message(msg.chat.id, response).await?;
-                    } else {
-                        bot.send_message(
-                            msg.chat.id,
-                            "Sorry, this type of message isn't supported.",
-                        )
-                        .await?;
-                    }
-                    Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
-                }),
-        );
+    // Submissions come from private chats, whose id is the participant's user id
+    let remaining_challenges = remaining_challenges(pool, chat_id.0).await?;
+    let tagged = tagged_challenge(&sub_ext.caption, &remaining_challenges);
+    let keyboard = make_keyboard(
+        chat_id.0 as u64,
+        message_id.0,
+        remaining_challenges,
+        tagged,
+        judge_keyboard_columns(pool).await?,
+    );
+    actions
+        .send_text(
+            judge_chat,
+            "Select challenge or action".to_string(),
+            SendOptions {
+                thread_id,
+                keyboard: Some(keyboard),
+                silent: true,
+                ..Default::default()
+            },
+        )
+        .await?;
 
-    let meta_handler = dptree::entry()
-        .branch(handler)
-        .branch(Update::filter_callback_query().endpoint(callback_handler));
+    warn_about_pending_submissions(actions, pool, judge_chat).await?;
 
-    Dispatcher::builder(bot, meta_handler)
-        .dependencies(dptree::deps![db, parameters, lock, submissions_enabled])
-        .default_handler(|upd| async move {
-            log::warn!("Unhandled update: {:?}", upd);
-        })
-        .error_handler(LoggingErrorHandler::with_custom_text(
-            "An error has occurred in the dispatcher",
-        ))
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+    Ok(true)
+}
+
+/// Submission whose request for a clear caption the participant replied to
+#[derive(Clone, Debug)]
+struct ClarifiedSubmission(MessageId);
+
+/// Replace the caption of a submission judged unclear and ask the judges again
+async fn clarify_submission(
+    msg: Message,
+    submission: ClarifiedSubmission,
+    actions: Actions,
+    cfg: ConfigParameters,
+    pool: SqlitePool,
+    queue: JudgeQueue,
+) -> BotResult {
+    let ClarifiedSubmission(submission_id) = submission;
+    let (caption, _) = truncate_caption(
+        msg.text().unwrap_or_default().trim(),
+        max_caption_length(&pool).await?,
+    );
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE submissions SET caption = $1, clarification_message_id = NULL
+        WHERE message_id = $2",
+    )
+    .bind(&caption)
+    .bind(submission_id.0)
+    .execute(&mut *tx)
+    .await?;
+    // Back in the queue of unjudged submissions
+    sqlx::query("DELETE FROM judgement WHERE submission_id = $1")
+        .bind(submission_id.0)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    queue
+        .push(
+            QueuedSubmission {
+                chat_id: msg.chat.id,
+                message_id: submission_id,
+                note: Some("🔁 caption clarified by the participant".to_owned()),
+                new: false,
+            },
+            &actions,
+            &cfg,
+            &pool,
+        )
+        .await?;
+    actions
+        .send_text(
+            msg.chat.id,
+            "Thanks, your submission will be judged again".to_owned(),
+            SendOptions {
+                reply_to: Some(submission_id),
+                ..Default::default()
+            },
+        )
+        .await?;
     Ok(())
 }
 
-fn make_keyboard(
-    associate: String,
-    reference: String,
-    challenges: Vec<Challenge>,
-) -> InlineKeyboardMarkup {
-    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+/// Tell the participant their media couldn't be processed instead of failing silently
+async fn report_unprocessable_file(actions: &impl BotActions, chat_id: ChatId) -> BotResult {
+    actions
+        .send_text(
+            chat_id,
+            "We couldn't process that file, please try a smaller one or try again".to_owned(),
+            SendOptions::default(),
+        )
+        .await?;
+    Ok(())
+}
 
-    for versions in challenges.chunks(1) {
-        let row = versions
-            .iter()
-            .map(|challenge| {
-                InlineKeyboardButton::callback(
-                    challenge.short_name.to_owned(),
-                    format!("{}###{}###{}", associate, reference, challenge.name),
+/// Ask the judges for help once the number of unjudged submissions reaches the
+/// `pending_submissions_warning_threshold` from the `config` table. Warns again only after
+/// the backlog dropped below the threshold, tracked in `pending_submissions_warned`.
+async fn warn_about_pending_submissions(
+    actions: &impl BotActions,
+    pool: &SqlitePool,
+    judge_chat: ChatId,
+) -> BotResult {
+    let Some(threshold) = config_value(pool, "pending_submissions_warning_threshold")
+        .await?
+        .and_then(|x| x.parse::<i64>().ok())
+    else {
+        return Ok(());
+    };
+    let pending: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM submissions s
+        LEFT JOIN judgement j ON j.submission_id = s.message_id
+        WHERE j.submission_id IS NULL AND NOT s.excluded AND NOT s.test",
+    )
+    .fetch_one(pool)
+    .await?;
+    // Several submissions can arrive between two checks, so the count may skip the threshold
+    let warned = config_value(pool, "pending_submissions_warned")
+        .await?
+        .as_deref()
+        == Some("1");
+    if pending < threshold {
+        if warned {
+            set_config_value(pool, "pending_submissions_warned", "0").await?;
+        }
+    } else if !warned {
+        set_config_value(pool, "pending_submissions_warned", "1").await?;
+        actions
+            .send_text(
+                judge_chat,
+                format!("⚠️ {} submissions pending, please help judge", pending),
+                SendOptions::default(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn maintainer_commands(
+    msg: Message,
+    bot: Bot,
+    actions: Actions,
+    cmd: MaintainerCommands,
+    pool: SqlitePool,
+    lock: Arc<Mutex<()>>,
+    submissions_enabled: Arc<AtomicBool>,
+    cfg: ConfigParameters,
+) -> BotResult {
+    match cmd {
+        MaintainerCommands::SetEventStart(start) => {
+            let start = start.trim();
+            let text = if start.is_empty() || start == "none" {
+                sqlx::query("DELETE FROM config WHERE name = 'event_start'")
+                    .execute(&pool)
+                    .await?;
+                "Event start cleared, the hunt is running".to_owned()
+            } else if let Some(date) = scoring::parse_date(start) {
+                set_config_value(
+                    &pool,
+                    "event_start",
+                    &date.format("%Y-%m-%d %H:%M:%S").to_string(),
                 )
-            })
-            .collect();
+                .await?;
+                format!("The hunt starts at {} UTC", date)
+            } else {
+                "Invalid date, use the format 2024-11-20 18:00:00".to_owned()
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::ScheduleEvent(event) => {
+            let offset = event_utc_offset(&pool).await?;
+            let event = event.trim();
+            if event.is_empty() {
+                let events = sqlx::query_as::<_, (i64, String, String)>(
+                    "SELECT id, starts_at, title FROM schedule ORDER BY starts_at",
+                )
+                .fetch_all(&pool)
+                .await?;
+                let text = if events.is_empty() {
+                    "The schedule is empty".to_owned()
+                } else {
+                    events
+                        .into_iter()
+                        .map(|(id, starts_at, title)| {
+                            let starts_at = scoring::parse_date(&starts_at)
+                                .map(|x| calendar::format_local(x, offset))
+                                .unwrap_or(starts_at);
+                            format!("{}: {} {}", id, starts_at, title)
+                        })
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                };
+                for chunk in split_message(&text) {
+                    bot.send_message(msg.chat.id, chunk).await?;
+                }
+                return Ok(());
+            }
+            // Date, time and a title
+            let mut parts = event.splitn(3, ' ');
+            let (Some(date), Some(time), Some(title)) = (parts.next(), parts.next(), parts.next())
+            else {
+                bot.send_message(
+                    msg.chat.id,
+                    "Use the format /schedule_event 2024-11-21 10:00 Walking tour",
+                )
+                .await?;
+                return Ok(());
+            };
+            let Ok(local) = chrono::NaiveDateTime::parse_from_str(
+                &format!("{} {}", date, time),
+                "%Y-%m-%d %H:%M",
+            ) else {
+                bot.send_message(msg.chat.id, "Invalid date, use the format 2024-11-21 10:00")
+                    .await?;
+                return Ok(());
+            };
+            let starts_at = local - offset;
+            let id = sqlx::query(
+                "INSERT INTO schedule (starts_at, title, reminded) VALUES ($1, $2, $3)",
+            )
+            .bind(starts_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .bind(title.trim())
+            .bind(starts_at <= chrono::Utc::now().naive_utc())
+            .execute(&pool)
+            .await?
+            .last_insert_rowid();
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Scheduled {} at {} as event {}",
+                    title.trim(),
+                    calendar::format_local(starts_at, offset),
+                    id
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::DeleteScheduleEvent { id } => {
+            let deleted = sqlx::query("DELETE FROM schedule WHERE id = $1")
+                .bind(id)
+                .execute(&pool)
+                .await?
+                .rows_affected();
+            let text = match deleted {
+                0 => "Event not found",
+                _ => "Event removed from the schedule",
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::ListTeams => {
+            let res =
+                sqlx::query_as::<_, Team>("SELECT DISTINCT team, COUNT(*) as count FROM users")
+                    .fetch_all(&pool)
+                    .await
+                    .unwrap();
+            let teams = res
+                .into_iter()
+                .map(|x| format!("- {} (#{})", x.team, x.count))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            bot.send_message(msg.chat.id, format!("Teams:\n{}", teams))
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::ListTeamMembers => {
+            let res = sqlx::query_as::<_, User>("SELECT * FROM users ORDER BY team")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            let users = res
+                .iter()
+                .map(|x| format!("- {} (#{}) -> {}", x, x.id, x.team))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            bot.send_message(msg.chat.id, format!("Participants:\n{}", users))
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::Scoreboard => {
+            bot.send_message(msg.chat.id, scoreboard_text(&pool).await?)
+                .await?;
+            refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+            Ok(())
+        }
+        MaintainerCommands::ChallengeLeaderboard => {
+            #[derive(sqlx::FromRow)]
+            struct Completed {
+                team: String,
+                challenges: i64,
+            }
+            let res = sqlx::query_as::<_, Completed>(
+                "SELECT s.team, COUNT(DISTINCT j.challenge_name) AS challenges
+                FROM judgement j
+                JOIN submissions s ON j.submission_id = s.message_id
+                WHERE j.valid = 1
+                GROUP BY s.team
+                ORDER BY challenges DESC, s.team",
+            )
+            .fetch_all(&pool)
+            .await?;
+            let emojis = team_emojis(&pool).await?;
+            // Teams with the same count share a place, the next place is skipped
+            let mut place = 0;
+            let lines = res
+                .iter()
+                .enumerate()
+                .map(|(index, x)| {
+                    if index == 0 || res[index - 1].challenges != x.challenges {
+                        place = index + 1;
+                    }
+                    let emoji = emojis
+                        .get(&x.team)
+                        .map(|emoji| format!("{} ", emoji))
+                        .unwrap_or_default();
+                    format!(
+                        "{}. {}`{}` with {} challenge(s)",
+                        place, emoji, x.team, x.challenges
+                    )
+                })
+                .collect::<Vec<String>>();
+            let text = match lines.is_empty() {
+                true => "No challenges completed yet".to_owned(),
+                false => format!("Challenges completed:\n{}", lines.join("\n")),
+            };
+            for chunk in split_message(&text) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::PinScoreboard => {
+            // Replace the previously pinned scoreboard
+            if let Some(old) = pinned_scoreboard(&pool).await? {
+                if let Err(e) = bot.unpin_chat_message(cfg.judge_chat).message_id(old).await {
+                    log::warn!("Could not unpin the previous scoreboard: {}", e);
+                }
+            }
+            let scoreboard = bot
+                .send_message(cfg.judge_chat, scoreboard_text(&pool).await?)
+                .await?;
+            match bot
+                .pin_chat_message(cfg.judge_chat, scoreboard.id)
+                .disable_notification(true)
+                .await
+            {
+                Ok(_) => {}
+                Err(RequestError::Api(
+                    ApiError::NotEnoughRightsToPinMessage | ApiError::NotEnoughRightsToManagePins,
+                )) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "I'm not allowed to pin messages in the judge chat. Give me the permission to pin messages and try again.",
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            }
+            set_config_value(
+                &pool,
+                "pinned_scoreboard_message_id",
+                &scoreboard.id.0.to_string(),
+            )
+            .await?;
+            bot.send_message(msg.chat.id, "Scoreboard pinned in the judge chat")
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::ListTeamSubmissions => {
+            let res = sqlx::query_as::<_, TeamScore>(
+                "SELECT s.team, SUM(j.points) as score
+                FROM judgement j
+                LEFT JOIN submissions s ON j.submission_id = s.message_id
+                LEFT JOIN users u ON s.team = u.team
+                WHERE j.valid = 1
+                GROUP BY s.team ORDER BY score DESC",
+            )
+            .fetch_all(&pool)
+            .await?;
+            for team in res {
+                let submissions = sqlx::query_as::<_, SubmissionExtended>(
+                    "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id
+                    FROM submissions s
+                    LEFT JOIN users u ON s.user = u.id
+                    WHERE s.team = $1",
+                )
+                .bind(team.clone().team)
+                .fetch_all(&pool)
+                .await?;
+                let submissions = submissions
+                    .iter()
+                    .map(submission_message)
+                    .collect::<Vec<String>>()
+                    .join("\n\n");
+                bot.send_message(
+                    msg.chat.id,
+                    format!("Submissions for team `{}`:\n{}", team.team, submissions),
+                )
+                .await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::UserSubmissions { username } => {
+            let username = username.trim_start_matches('@');
+            let user_id: Option<i64> =
+                sqlx::query_scalar("SELECT id FROM users WHERE username = $1 COLLATE NOCASE")
+                    .bind(username)
+                    .fetch_optional(&pool)
+                    .await?;
+            let Some(user_id) = user_id else {
+                bot.send_message(msg.chat.id, format!("User @{} not found", username))
+                    .await?;
+                return Ok(());
+            };
+            #[derive(sqlx::FromRow)]
+            struct JudgedSubmission {
+                #[sqlx(flatten)]
+                submission: SubmissionExtended,
+                challenge_name: Option<String>,
+                points: Option<i32>,
+                valid: Option<bool>,
+            }
+            // Only this participant's submissions, not those of the rest of the team
+            let submissions = sqlx::query_as::<_, JudgedSubmission>(
+                "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id,
+                    j.challenge_name, j.points, j.valid
+                FROM submissions s
+                LEFT JOIN users u ON s.user = u.id
+                LEFT JOIN judgement j ON j.submission_id = s.message_id
+                WHERE s.user = $1
+                ORDER BY s.date",
+            )
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await?;
+            if submissions.is_empty() {
+                bot.send_message(msg.chat.id, format!("@{} has no submissions", username))
+                    .await?;
+                return Ok(());
+            }
+            let points = points_format(&pool).await;
+            let text = submissions
+                .iter()
+                .map(|x| {
+                    let verdict = match (&x.challenge_name, x.valid) {
+                        (Some(challenge), Some(true)) => format!(
+                            "✅ {} ({})",
+                            challenge,
+                            format_points(x.points.unwrap_or_default().into(), &points)
+                        ),
+                        (Some(challenge), _) => format!("❌ {}", challenge),
+                        (None, _) => "⏳ not judged yet".to_owned(),
+                    };
+                    format!(
+                        "{}\nVerdict: {}",
+                        submission_message(&x.submission),
+                        verdict
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n\n");
+            let text = format!(
+                "{} submission(s) by @{}:\n\n{}",
+                submissions.len(),
+                username,
+                text
+            );
+            for chunk in split_message(&text) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::ListTeamSubmissionJudgments => {
+            let res = sqlx::query_as::<_, TeamScore>(TEAM_SCORES_QUERY)
+                .fetch_all(&pool)
+                .await?;
+            let points = points_format(&pool).await;
+            for team in res {
+                let judgements = sqlx::query_as::<_, Judgement>(
+                    "SELECT j.submission_id, j.challenge_name, j.points, j.valid
+                    FROM judgement j
+                    LEFT JOIN submissions s ON j.submission_id = s.message_id
+                    WHERE s.team = $1",
+                )
+                .bind(team.clone().team)
+                .fetch_all(&pool)
+                .await?;
+                let judgements = judgements
+                    .iter()
+                    .map(|x| {
+                        format!(
+                            "- ref=`{}` challenge=`{}` {} valid={}",
+                            x.submission_id,
+                            x.challenge_name,
+                            format_points(x.points.into(), &points),
+                            x.valid
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                let adjustments = sqlx::query_as::<_, Adjustment>(
+                    "SELECT points, reason, created_at FROM adjustments WHERE team = $1",
+                )
+                .bind(team.clone().team)
+                .fetch_all(&pool)
+                .await?;
+                let adjustments = if adjustments.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        "\n\nAdjustments:\n{}",
+                        adjustments
+                            .iter()
+                            .map(|x| format!(
+                                "- {} {} reason=`{}`",
+                                x.created_at,
+                                format_points_change(x.points.into(), &points),
+                                x.reason
+                            ))
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    )
+                };
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "Judgements for team `{}`:\n{}{}",
+                        team.team, judgements, adjustments
+                    ),
+                )
+                .await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::JudgeStats => {
+            let stats: Vec<(Option<i64>, i64, i64)> = sqlx::query_as(
+                "SELECT judge_user_id, COUNT(*), SUM(valid)
+                FROM judgement
+                GROUP BY judge_user_id
+                ORDER BY COUNT(*) DESC",
+            )
+            .fetch_all(&pool)
+            .await?;
+            if stats.is_empty() {
+                bot.send_message(msg.chat.id, "No judgements yet").await?;
+                return Ok(());
+            }
+            let mut lines = vec![];
+            for (judge_id, count, accepted) in stats {
+                // Judges are maintainers, who usually aren't participants with a stored name
+                let name = match judge_id {
+                    Some(id) => {
+                        match bot.get_chat_member(cfg.judge_chat, UserId(id as u64)).await {
+                            Ok(member) => html::escape(&member.user.full_name()),
+                            Err(_) => format!("<code>{}</code>", id),
+                        }
+                    }
+                    None => "<i>unknown</i>".to_owned(),
+                };
+                lines.push(format!(
+                    "- {}: {} judgement(s), {:.0}% accepted",
+                    name,
+                    count,
+                    accepted as f64 * 100.0 / count as f64
+                ));
+            }
+            bot.send_message(
+                msg.chat.id,
+                format!("<b>Judgements per judge</b>\n{}", lines.join("\n")),
+            )
+            .parse_mode(ParseMode::Html)
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::ListConfig => {
+            let entries =
+                sqlx::query_as::<_, Config>("SELECT name, value FROM config ORDER BY name")
+                    .fetch_all(&pool)
+                    .await?;
+            if entries.is_empty() {
+                bot.send_message(msg.chat.id, "The config table is empty")
+                    .await?;
+                return Ok(());
+            }
+            let lines = entries
+                .iter()
+                .map(|entry| {
+                    let name = entry.name.to_lowercase();
+                    let value = if name.contains("token") || name.contains("secret") {
+                        "<i>hidden</i>".to_owned()
+                    } else {
+                        format!("<code>{}</code>", html::escape(&entry.value))
+                    };
+                    format!("- {}: {}", html::escape(&entry.name), value)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            for chunk in split_message(&format!("<b>Config</b>\n{}", lines)) {
+                bot.send_message(msg.chat.id, chunk)
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::RecomputeScores => {
+            award_all_podium_bonuses(&pool).await?;
+            let scores = sqlx::query_as::<_, TeamScore>(TEAM_SCORES_QUERY)
+                .fetch_all(&pool)
+                .await?;
+
+            let mut violations = vec![];
+            let orphans: Vec<i64> = sqlx::query_scalar(
+                "SELECT j.submission_id
+                FROM judgement j
+                LEFT JOIN submissions s ON j.submission_id = s.message_id
+                WHERE s.message_id IS NULL",
+            )
+            .fetch_all(&pool)
+            .await?;
+            violations.extend(
+                orphans
+                    .iter()
+                    .map(|id| format!("- judgement ref=`{}` has no submission", id)),
+            );
+            let duplicates: Vec<(String, String, i64)> = sqlx::query_as(
+                "SELECT s.team, j.challenge_name, COUNT(*)
+                FROM judgement j
+                JOIN submissions s ON j.submission_id = s.message_id
+                WHERE j.valid = 1
+                GROUP BY s.team, j.challenge_name
+                HAVING COUNT(*) > 1",
+            )
+            .fetch_all(&pool)
+            .await?;
+            violations.extend(duplicates.iter().map(|(team, challenge, count)| {
+                format!(
+                    "- team `{}` has {} valid judgements for challenge `{}`",
+                    team, count, challenge
+                )
+            }));
+            let unknown_challenges: Vec<(i64, String)> = sqlx::query_as(
+                "SELECT j.submission_id, j.challenge_name
+                FROM judgement j
+                LEFT JOIN challenges c ON j.challenge_name = c.name
+                WHERE j.valid = 1 AND c.name IS NULL",
+            )
+            .fetch_all(&pool)
+            .await?;
+            violations.extend(unknown_challenges.iter().map(|(id, challenge)| {
+                format!(
+                    "- judgement ref=`{}` references unknown challenge `{}`",
+                    id, challenge
+                )
+            }));
+            let excluded: Vec<i64> = sqlx::query_scalar(
+                "SELECT j.submission_id
+                FROM judgement j
+                JOIN submissions s ON j.submission_id = s.message_id
+                WHERE j.valid = 1 AND s.excluded",
+            )
+            .fetch_all(&pool)
+            .await?;
+            violations.extend(excluded.iter().map(|id| {
+                format!(
+                    "- judgement ref=`{}` is valid but its submission is excluded",
+                    id
+                )
+            }));
+
+            let points = points_format(&pool).await;
+            let scores = scores
+                .iter()
+                .map(|x| format!("- `{}`: {}", x.team, format_points(x.score, &points)))
+                .collect::<Vec<String>>()
+                .join("\n");
+            let violations = if violations.is_empty() {
+                "No violations found".to_owned()
+            } else {
+                format!(
+                    "{} violation(s):\n{}",
+                    violations.len(),
+                    violations.join("\n")
+                )
+            };
+            for chunk in split_message(&format!("Recomputed scores:\n{}\n\n{}", scores, violations))
+            {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::AdjustScore {
+            team,
+            points,
+            reason,
+        } => {
+            let known_team = sqlx::query("SELECT id FROM users WHERE team = $1 LIMIT 1")
+                .bind(team.clone())
+                .fetch_optional(&pool)
+                .await?
+                .is_some();
+            if !known_team {
+                bot.send_message(msg.chat.id, format!("Team `{}` not found", team))
+                    .await?;
+                return Ok(());
+            }
+            sqlx::query(
+                "INSERT INTO adjustments (team, points, reason, created_by, created_at)
+                VALUES ($1, $2, $3, $4, datetime('now'))",
+            )
+            .bind(team.clone())
+            .bind(points)
+            .bind(reason.clone())
+            .bind(sender(&msg)?.id.0 as i64)
+            .execute(&pool)
+            .await?;
+            let format = points_format(&pool).await;
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Adjusted team `{}` by {} ({}), new score: {}",
+                    team,
+                    format_points_change(points.into(), &format),
+                    reason,
+                    format_points(team_score(&pool, &team).await?, &format)
+                ),
+            )
+            .await?;
+            refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+            Ok(())
+        }
+        MaintainerCommands::CreateTeam(team) => {
+            let team = team.trim();
+            if team.is_empty() {
+                bot.send_message(msg.chat.id, "Please provide a team name")
+                    .await?;
+                return Ok(());
+            }
+            let existing: Option<String> =
+                sqlx::query_scalar("SELECT code FROM teams WHERE name = $1")
+                    .bind(team)
+                    .fetch_optional(&pool)
+                    .await?;
+            let code = match existing {
+                Some(code) => code,
+                None => loop {
+                    let code = join_code();
+                    // Retry in the unlikely case the code is already taken
+                    let inserted = sqlx::query(
+                        "INSERT INTO teams (name, code, created_at) VALUES ($1, $2, datetime('now'))
+                        ON CONFLICT(code) DO NOTHING",
+                    )
+                    .bind(team)
+                    .bind(code.clone())
+                    .execute(&pool)
+                    .await?;
+                    if inserted.rows_affected() == 1 {
+                        break code;
+                    }
+                },
+            };
+            bot.send_message(
+                msg.chat.id,
+                format!("Team `{}` joins with /join_team {}", team, code),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::TeamRemaining { team } => {
+            let team = team.trim();
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM teams WHERE name = $1)
+                    OR EXISTS(SELECT 1 FROM users WHERE team = $1)",
+            )
+            .bind(team)
+            .fetch_one(&pool)
+            .await?;
+            if !exists {
+                bot.send_message(msg.chat.id, format!("Team {:?} not found", team))
+                    .await?;
+                return Ok(());
+            }
+            let (open, locked) = team_open_and_locked_challenges(&pool, team).await?;
+            let text = match open_challenges_text(&open, &locked) {
+                Some(text) => format!("<b>{}</b>\n\n{}", html::escape(team), text),
+                None => format!("{} completed all challenges", html::escape(team)),
+            };
+            for chunk in split_message(&text) {
+                bot.send_message(msg.chat.id, chunk)
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::ChallengeStats => {
+            // Challenges without completions are included to spot the neglected ones
+            let stats: Vec<(String, i64)> = sqlx::query_as(
+                "SELECT c.name, COUNT(DISTINCT s.team) AS completions
+                FROM challenges c
+                LEFT JOIN judgement j ON j.challenge_name = c.name AND j.valid = 1
+                LEFT JOIN submissions s ON j.submission_id = s.message_id
+                GROUP BY c.name
+                ORDER BY completions DESC, c.name",
+            )
+            .fetch_all(&pool)
+            .await?;
+            let stats = stats
+                .iter()
+                .map(|(name, completions)| format!("- `{}`: {} team(s)", name, completions))
+                .collect::<Vec<String>>()
+                .join("\n");
+            for chunk in split_message(&format!("Challenge completions:\n{}", stats)) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::UpdateTeamForums => {
+            // A forced update also undoes /close_all_forums
+            if forums_closed(&pool).await? {
+                set_config_value(&pool, "forums_closed", "false").await?;
+                bot.send_message(msg.chat.id, "Reopening the team topics")
+                    .await?;
+            }
+            update_teams_in_forum(&actions, &pool, &lock, &cfg.forum_chat).await?;
+            Ok(())
+        }
+        MaintainerCommands::PostTeamGallery(team) => {
+            let team = team.trim();
+            let forum_id = || async {
+                sqlx::query_scalar::<_, i32>("SELECT id FROM forums WHERE name = $1")
+                    .bind(team)
+                    .fetch_optional(&pool)
+                    .await
+            };
+            let mut thread_id = forum_id().await?;
+            if thread_id.is_none() {
+                // Teams get their topic with the next reconciliation, don't wait for it
+                update_teams_in_forum(&actions, &pool, &lock, &cfg.forum_chat).await?;
+                thread_id = forum_id().await?;
+            }
+            let Some(thread_id) = thread_id else {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "Team `{}` has no forum topic and none could be created",
+                        team
+                    ),
+                )
+                .await?;
+                return Ok(());
+            };
+            let mut media = submission_media(&pool, team).await?;
+            if media.is_empty() {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("Team `{}` has no submissions with a stored file", team),
+                )
+                .await?;
+                return Ok(());
+            }
+            send_gallery(
+                &bot,
+                cfg.forum_chat.clone(),
+                Some(ThreadId(MessageId(thread_id))),
+                &mut media,
+            )
+            .await?;
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Posted {} photo(s) and video(s) of `{}` into its topic",
+                    media.len(),
+                    team
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::CloseAllForums(confirmation) => {
+            let _guard = lock.lock().await;
+            let forums = sqlx::query_as::<_, Forum>(
+                "SELECT id, name, open, title FROM forums WHERE open = 1",
+            )
+            .fetch_all(&pool)
+            .await?;
+            if confirmation.trim() != "confirm" {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "This closes the {} open team topic(s) and stops them from being reopened until /update_team_forums.\n\nConfirm with /close_all_forums confirm",
+                        forums.len()
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            // Set first so a reconciliation in between doesn't reopen the closed topics
+            set_config_value(&pool, "forums_closed", "true").await?;
+            let mut failed = vec![];
+            for forum in &forums {
+                let closed = actions
+                    .close_topic(cfg.forum_chat.clone(), ThreadId(MessageId(forum.id)))
+                    .await;
+                match closed {
+                    Ok(()) => {
+                        sqlx::query("UPDATE forums SET open = 0 WHERE id = $1")
+                            .bind(forum.id)
+                            .execute(&pool)
+                            .await?;
+                    }
+                    Err(e) => {
+                        log::warn!("Could not close the topic of {}: {}", forum.name, e);
+                        failed.push(forum.name.clone());
+                    }
+                }
+            }
+            let mut text = format!(
+                "Closed {} of {} team topic(s)",
+                forums.len() - failed.len(),
+                forums.len()
+            );
+            if !failed.is_empty() {
+                text.push_str(&format!(
+                    "\nFailed, run the command again to retry: {}",
+                    failed.join(", ")
+                ));
+            }
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::ResetTeam { team, confirmation } => {
+            let mut tx = pool.begin().await?;
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM teams WHERE name = $1)
+                    OR EXISTS(SELECT 1 FROM users WHERE team = $1)",
+            )
+            .bind(&team)
+            .fetch_one(&mut *tx)
+            .await?;
+            if !exists {
+                bot.send_message(msg.chat.id, format!("Team {:?} not found", team))
+                    .await?;
+                return Ok(());
+            }
+            let (submissions, judgements): (i64, i64) = sqlx::query_as(
+                "SELECT COUNT(*), COUNT(j.submission_id)
+                FROM submissions s
+                LEFT JOIN judgement j ON j.submission_id = s.message_id
+                WHERE s.team = $1",
+            )
+            .bind(&team)
+            .fetch_one(&mut *tx)
+            .await?;
+            // The second confirmation repeats the count, so it can't be sent blindly or after
+            // the team submitted more
+            let expected = format!("confirm {}", submissions);
+            match confirmation.as_str() {
+                "" => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!(
+                            "This deletes the {} submission(s) and {} judgement(s) of {}.\n\nConfirm with /reset_team \"{}\" confirm",
+                            submissions, judgements, team, team
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                "confirm" => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!(
+                            "Are you sure? {} loses all {} submission(s) and its score from them, this can't be undone.\n\nConfirm again with /reset_team \"{}\" {}",
+                            team, submissions, team, expected
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                confirmation if confirmation != expected => {
+                    bot.send_message(
+                        msg.chat.id,
+                        format!(
+                            "The team has {} submission(s) now, start again with /reset_team \"{}\"",
+                            submissions, team
+                        ),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                _ => {}
+            }
+
+            let deleted_judgements = sqlx::query(
+                "DELETE FROM judgement
+                WHERE submission_id IN (SELECT message_id FROM submissions WHERE team = $1)",
+            )
+            .bind(&team)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            let deleted_submissions = sqlx::query("DELETE FROM submissions WHERE team = $1")
+                .bind(&team)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            tx.commit().await?;
+            // Teams behind the reset one move up
+            award_all_podium_bonuses(&pool).await?;
+            log::info!(
+                "Reset team {}: deleted {} submission(s), {} judgement(s)",
+                team,
+                deleted_submissions,
+                deleted_judgements
+            );
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Deleted {} submission(s) and {} judgement(s) of {}",
+                    deleted_submissions, deleted_judgements, team
+                ),
+            )
+            .await?;
+            refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+            Ok(())
+        }
+        MaintainerCommands::CloseForum { ref team }
+        | MaintainerCommands::ReopenForum { ref team } => {
+            let open = matches!(cmd, MaintainerCommands::ReopenForum { .. });
+            let team = team.trim();
+            let _guard = lock.lock().await;
+            let forum = sqlx::query_as::<_, Forum>(
+                "SELECT id, name, open, title FROM forums WHERE name = $1",
+            )
+            .bind(team)
+            .fetch_optional(&pool)
+            .await?;
+            let Some(forum) = forum else {
+                bot.send_message(msg.chat.id, format!("No forum topic for team {}", team))
+                    .await?;
+                return Ok(());
+            };
+            if forum.open == open {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "The topic of {} is already {}",
+                        team,
+                        if open { "open" } else { "closed" }
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            let thread_id = ThreadId(MessageId(forum.id));
+            if open {
+                actions
+                    .reopen_topic(cfg.forum_chat.clone(), thread_id)
+                    .await?;
+            } else {
+                actions
+                    .close_topic(cfg.forum_chat.clone(), thread_id)
+                    .await?;
+            }
+            // The reconciliation leaves a topic closed here alone until it is reopened here
+            sqlx::query("UPDATE forums SET open = $1, closed_manually = $2 WHERE id = $3")
+                .bind(open)
+                .bind(!open)
+                .bind(forum.id)
+                .execute(&pool)
+                .await?;
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "{} the topic of {}",
+                    if open { "Reopened" } else { "Closed" },
+                    team
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::RecentActivity => {
+            let activity: Vec<(String, Option<String>)> = sqlx::query_as(
+                "SELECT t.team, MAX(s.date) AS latest
+                FROM (SELECT team FROM users UNION SELECT name FROM teams) t
+                LEFT JOIN submissions s ON s.team = t.team
+                GROUP BY t.team
+                ORDER BY latest IS NULL, latest DESC",
+            )
+            .fetch_all(&pool)
+            .await?;
+            let now = chrono::Utc::now().naive_utc();
+            let lines = activity
+                .iter()
+                .map(|(team, latest)| {
+                    let latest = match latest.as_deref().and_then(scoring::parse_date) {
+                        Some(latest) => calendar::time_ago(now, latest),
+                        None => "no submissions".to_owned(),
+                    };
+                    format!("- {}: {}", team, latest)
+                })
+                .collect::<Vec<String>>();
+            let text = if lines.is_empty() {
+                "No teams yet".to_owned()
+            } else {
+                format!("Latest submission per team:\n{}", lines.join("\n"))
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::InactiveUsers { hours } => {
+            let since = chrono::Utc::now().naive_utc() - chrono::Duration::hours(hours);
+            #[derive(sqlx::FromRow)]
+            struct InactiveUser {
+                #[sqlx(flatten)]
+                user: User,
+                last_active: Option<String>,
+            }
+            let inactive = sqlx::query_as::<_, InactiveUser>(
+                "SELECT id, team, username, first_name, last_name, last_active FROM users
+                WHERE last_active IS NULL OR last_active < $1
+                ORDER BY last_active IS NOT NULL, last_active",
+            )
+            .bind(since.format("%Y-%m-%d %H:%M:%S").to_string())
+            .fetch_all(&pool)
+            .await?;
+            if inactive.is_empty() {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("Everyone was active in the last {} hour(s)", hours),
+                )
+                .await?;
+                return Ok(());
+            }
+            let now = chrono::Utc::now().naive_utc();
+            let lines = inactive
+                .iter()
+                .map(|InactiveUser { user, last_active }| {
+                    let seen = match last_active.as_deref().and_then(scoring::parse_date) {
+                        Some(last_active) => calendar::time_ago(now, last_active),
+                        None => "not seen yet".to_owned(),
+                    };
+                    format!("- {} ({}): {}", user, user.team, seen)
+                })
+                .collect::<Vec<String>>();
+            let text = format!(
+                "{} participant(s) inactive for {} hour(s) or more:\n{}",
+                inactive.len(),
+                hours,
+                lines.join("\n")
+            );
+            for chunk in split_message(&text) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::MergeUsers { from_id, into_id } => {
+            if from_id == into_id {
+                bot.send_message(msg.chat.id, "Both IDs are the same")
+                    .await?;
+                return Ok(());
+            }
+            let mut tx = pool.begin().await?;
+            let from_team: Option<String> =
+                sqlx::query_scalar("SELECT team FROM users WHERE id = $1")
+                    .bind(from_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            let Some(from_team) = from_team else {
+                bot.send_message(msg.chat.id, format!("No participant with ID {}", from_id))
+                    .await?;
+                return Ok(());
+            };
+            let into_team: Option<String> =
+                sqlx::query_scalar("SELECT team FROM users WHERE id = $1")
+                    .bind(into_id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            // The new account joins the team of the old one
+            if into_team.is_some() {
+                sqlx::query("UPDATE users SET team = $1 WHERE id = $2")
+                    .bind(&from_team)
+                    .bind(into_id)
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("DELETE FROM users WHERE id = $1")
+                    .bind(from_id)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                sqlx::query("UPDATE users SET id = $1 WHERE id = $2")
+                    .bind(into_id)
+                    .bind(from_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            // Judgements key on the submission, they move along
+            let moved = sqlx::query("UPDATE submissions SET user = $1 WHERE user = $2")
+                .bind(into_id)
+                .bind(from_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            for query in [
+                "UPDATE team_changes SET user_id = $1 WHERE user_id = $2",
+                "UPDATE feedback SET user_id = $1 WHERE user_id = $2",
+            ] {
+                sqlx::query(query)
+                    .bind(into_id)
+                    .bind(from_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            sqlx::query("DELETE FROM user_preferences WHERE user_id = $1")
+                .bind(from_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            let mut text = format!(
+                "Moved {} submission(s) from {} to {}, who is now in team {}",
+                moved, from_id, into_id, from_team
+            );
+            if let Some(into_team) = into_team.filter(|team| *team != from_team) {
+                text.push_str(&format!(" (was {})", into_team));
+            }
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::ListTeamChanges => {
+            let changes = sqlx::query_as::<_, TeamChange>(
+                "SELECT c.user_id, u.first_name, c.old_team, c.new_team, c.changed_at
+                FROM team_changes c
+                LEFT JOIN users u ON c.user_id = u.id
+                ORDER BY c.changed_at",
+            )
+            .fetch_all(&pool)
+            .await?;
+            let changes = if changes.is_empty() {
+                "No team changes".to_owned()
+            } else {
+                changes
+                    .iter()
+                    .map(|x| {
+                        format!(
+                            "- {}: {} (#{}) `{}` -> `{}`",
+                            x.changed_at,
+                            x.first_name.clone().unwrap_or("-".to_owned()),
+                            x.user_id,
+                            x.old_team,
+                            x.new_team
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            };
+            for chunk in split_message(&format!("Team changes:\n{}", changes)) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::CleanupFiles {
+            older_than_days,
+            only_judged,
+        } => {
+            let threshold = std::time::SystemTime::now()
+                - std::time::Duration::from_secs(older_than_days.max(0) as u64 * 24 * 60 * 60);
+            let (mut removed, mut freed) = (0, 0);
+            let mut entries = fs::read_dir(&cfg.submissions_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let metadata = entry.metadata().await?;
+                if !metadata.is_file() || metadata.modified()? > threshold {
+                    continue;
+                }
+                let path = entry.path().to_string_lossy().to_string();
+                if only_judged {
+                    let judged = sqlx::query(
+                        "SELECT j.submission_id
+                        FROM submissions s
+                        JOIN judgement j ON j.submission_id = s.message_id
+                        WHERE s.file_path = $1 OR s.thumbnail_path = $1",
+                    )
+                    .bind(path.clone())
+                    .fetch_optional(&pool)
+                    .await?
+                    .is_some();
+                    if !judged {
+                        continue;
+                    }
+                }
+                match fs::remove_file(entry.path()).await {
+                    Ok(()) => {
+                        removed += 1;
+                        freed += metadata.len();
+                    }
+                    Err(e) => log::warn!("Failed to remove {:?}: {}", path, e),
+                }
+            }
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Removed {} file(s), freed {:.1} MB",
+                    removed,
+                    freed as f64 / 1_000_000.0
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::BackupDb => {
+            // VACUUM INTO writes a consistent snapshot from within a read transaction, including
+            // pages still in the WAL, so the copy is usable on its own. It refuses to overwrite
+            // an existing file, hence the unique name.
+            let path = env::temp_dir().join(format!(
+                "spreebreak-backup-{}.sqlite",
+                chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")
+            ));
+            sqlx::query("VACUUM INTO $1")
+                .bind(path.to_string_lossy().to_string())
+                .execute(&pool)
+                .await?;
+            let sent = bot.send_document(msg.chat.id, InputFile::file(&path)).await;
+            if let Err(e) = fs::remove_file(&path).await {
+                log::warn!("Could not remove backup {:?}: {}", path, e);
+            }
+            sent?;
+            Ok(())
+        }
+        MaintainerCommands::SendTeamResults(confirmation) => {
+            // Teams which already got their results are skipped, so a failed run can be resumed
+            let teams: Vec<String> = sqlx::query_scalar(
+                "SELECT DISTINCT team FROM users
+                WHERE team NOT IN (SELECT team FROM team_results_sent)",
+            )
+            .fetch_all(&pool)
+            .await?;
+            if confirmation.trim() != "confirm" {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "This sends each of the {} remaining team(s) all of its submissions and its score breakdown. Teams that already got their results are skipped.\n\nConfirm with /send_team_results confirm",
+                        teams.len()
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            let (mut delivered, mut failed) = (0, 0);
+            for team in teams.iter() {
+                let members = sqlx::query_as::<_, User>("SELECT * FROM users WHERE team = $1")
+                    .bind(team)
+                    .fetch_all(&pool)
+                    .await?;
+                let judgements = sqlx::query_as::<_, Judgement>(
+                    "SELECT j.submission_id, j.challenge_name, j.points, j.valid
+                    FROM judgement j
+                    JOIN submissions s ON j.submission_id = s.message_id
+                    WHERE s.team = $1 AND j.valid = 1",
+                )
+                .bind(team)
+                .fetch_all(&pool)
+                .await?;
+                let points = points_format(&pool).await;
+                let breakdown = judgements
+                    .iter()
+                    .map(|x| {
+                        format!(
+                            "- {} {}",
+                            x.challenge_name,
+                            format_points_change(x.points.into(), &points)
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                let total: i32 = judgements.iter().map(|x| x.points).sum();
+                let text = format!(
+                    "Thanks for playing! Results of team {}:\n{}\n\nTotal score: {}\n\nHere are all of your team's submissions as a keepsake.",
+                    team,
+                    breakdown,
+                    format_points(total.into(), &points)
+                );
+                let mut media = submission_media(&pool, team).await?;
+
+                let mut team_failed = 0;
+                for member in members {
+                    let chat_id = UserId(member.id as u64);
+                    let result = async {
+                        bot.send_message(chat_id, text.clone()).await?;
+                        send_gallery(&bot, chat_id.into(), None, &mut media).await
+                    }
+                    .await;
+                    match result {
+                        Ok(()) => delivered += 1,
+                        Err(e) => {
+                            log::warn!("Sending results to {} failed: {}", member, e);
+                            team_failed += 1;
+                        }
+                    }
+                }
+                failed += team_failed;
+                // Teams with a failed member are sent again by the next run
+                if team_failed > 0 {
+                    continue;
+                }
+                sqlx::query(
+                    "INSERT INTO team_results_sent (team, sent_at) VALUES ($1, datetime('now'))",
+                )
+                .bind(team)
+                .execute(&pool)
+                .await?;
+            }
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Results of {} team(s) sent to {} participant(s), {} failed",
+                    teams.len(),
+                    delivered,
+                    failed
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::EnableSubmissions { status } => {
+            submissions_enabled.store(status, Ordering::Relaxed);
+            Ok(())
+        }
+        MaintainerCommands::ListParticipants => {
+            let users = sqlx::query_as::<_, User>("SELECT * FROM users")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            let users = users
+                .iter()
+                .map(|x| format!("- {} (#{})", x, x.id))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            bot.send_message(msg.chat.id, format!("Participants:\n{}", users))
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::MessageToParticipants(message) => {
+            if message.is_empty() {
+                bot.send_message(msg.chat.id, "Broadcast error: Empty message")
+                    .await?;
+                return Ok(());
+            }
+            // Query over all users and send a message to each of them
+            let users = sqlx::query_as::<_, User>("SELECT * FROM users")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            let (delivered, failed) = broadcast(
+                &bot,
+                &cfg,
+                sender(&msg)?,
+                users,
+                &message,
+                broadcast_parse_mode(&pool).await,
+            )
+            .await;
+            bot.send_message(
+                msg.chat.id,
+                format!("Message sent to {delivered} participant(s), {failed} failed"),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::PreviewBroadcast { message } => {
+            if message.is_empty() {
+                bot.send_message(msg.chat.id, "Broadcast error: Empty message")
+                    .await?;
+                return Ok(());
+            }
+            let parse_mode = broadcast_parse_mode(&pool).await;
+            match broadcast_message(&bot, msg.chat.id, &message, parse_mode).await {
+                Ok(_) => {
+                    bot.send_message(
+                        msg.chat.id,
+                        "This is how participants will see it. Send it with /message_to_participants followed by the same message.",
+                    )
+                    .await?;
+                }
+                // Most likely broken formatting, which would fail for every participant
+                Err(RequestError::Api(e)) => {
+                    bot.send_message(msg.chat.id, format!("The message can't be sent: {}", e))
+                        .await?;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            Ok(())
+        }
+        MaintainerCommands::MessageToPendingTeams(message) => {
+            let message = if message.trim().is_empty() {
+                "Your submissions are being judged, results are coming soon!".to_owned()
+            } else {
+                message
+            };
+            // Teams with the most unjudged submissions first
+            let teams = sqlx::query_as::<_, Team>(
+                "SELECT s.team, COUNT(*) AS count
+                FROM submissions s
+                LEFT JOIN judgement j ON j.submission_id = s.message_id
+                WHERE j.submission_id IS NULL AND NOT s.excluded AND NOT s.test
+                GROUP BY s.team ORDER BY count DESC",
+            )
+            .fetch_all(&pool)
+            .await?;
+            let mut users = vec![];
+            for team in &teams {
+                users.extend(
+                    sqlx::query_as::<_, User>("SELECT * FROM users WHERE team = $1")
+                        .bind(&team.team)
+                        .fetch_all(&pool)
+                        .await?,
+                );
+            }
+            let (delivered, failed) = broadcast(
+                &bot,
+                &cfg,
+                sender(&msg)?,
+                users,
+                &message,
+                broadcast_parse_mode(&pool).await,
+            )
+            .await;
+            let pending = teams
+                .iter()
+                .map(|team| format!("- {}: {} pending", team.team, team.count))
+                .collect::<Vec<_>>()
+                .join("\n");
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Message sent to {delivered} member(s) of {} team(s), {failed} failed\n{pending}",
+                    teams.len()
+                ),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::PushAssets => {
+            let mut schedule =
+                asset_file(&pool, "schedule_source", DEFAULT_SCHEDULE_SOURCE).await?;
+            let mut guide = asset_file(&pool, "city_guide", DEFAULT_SURVIVAL_GUIDE_SOURCE).await?;
+            let users = sqlx::query_as::<_, User>("SELECT * FROM users")
+                .fetch_all(&pool)
+                .await?;
+            let (mut delivered, mut failed) = (0, 0);
+            for user in users {
+                let chat_id = UserId(user.id as u64);
+                let result = async {
+                    let sent = bot.send_photo(chat_id, schedule.clone()).await?;
+                    // Reuse the uploaded file instead of uploading it once per participant
+                    if let Some(photo) = sent.photo().and_then(|sizes| sizes.last()) {
+                        schedule = InputFile::file_id(photo.file.id.clone());
+                    }
+                    let sent = bot.send_document(chat_id, guide.clone()).await?;
+                    if let Some(document) = sent.document() {
+                        guide = InputFile::file_id(document.file.id.clone());
+                    }
+                    Ok::<_, teloxide::RequestError>(())
+                }
+                .await;
+                match result {
+                    Ok(()) => delivered += 1,
+                    Err(e) => {
+                        log::warn!("Pushing assets to {} failed: {}", user, e);
+                        failed += 1;
+                    }
+                }
+            }
+            bot.send_message(
+                msg.chat.id,
+                format!("Assets pushed to {delivered} participant(s), {failed} failed"),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::ReopenJudging { message_id } => {
+            let submission: Option<(i64, Option<i32>, Option<i64>)> = sqlx::query_as(
+                "SELECT user, forwarded_message_id, judge_chat_id FROM submissions WHERE message_id = $1",
+            )
+            .bind(message_id)
+            .fetch_optional(&pool)
+            .await?;
+            let Some((associate, forwarded_id, judge_chat_id)) = submission else {
+                bot.send_message(msg.chat.id, "Submission not found")
+                    .await?;
+                return Ok(());
+            };
+            let sub_ext = sqlx::query_as::<_, SubmissionExtended>(
+                "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, f.id AS forum_id
+                FROM submissions s
+                LEFT JOIN users u ON s.user = u.id
+                LEFT JOIN forums f ON s.team = f.name
+                WHERE s.message_id = $1",
+            )
+            .bind(message_id)
+            .fetch_one(&pool)
+            .await?;
+            let judgement: Option<String> =
+                sqlx::query_scalar("SELECT challenge_name FROM judgement WHERE submission_id = $1")
+                    .bind(message_id)
+                    .fetch_optional(&pool)
+                    .await?;
+
+            let judge_chat = match judge_chat_id {
+                Some(judge_chat_id) => ChatId(judge_chat_id),
+                None => cfg.judge_chat_for(&sub_ext.team),
+            };
+            let mut text = submission_message(&sub_ext);
+            if let Some(challenge) = judgement {
+                text.push_str(&format!(
+                    "\nAlready judged as {}, a new choice overwrites it",
+                    challenge
+                ));
+            }
+            // Next to the forwarded submission if there is one, otherwise in the team's topic
+            let thread_id = sub_ext
+                .forum_id
+                .filter(|_| {
+                    forwarded_id.is_none()
+                        && cfg.judge_chat_is_forum
+                        && judge_chat == cfg.judge_chat
+                })
+                .map(|thread_id| ThreadId(MessageId(thread_id)));
+            let challenges = remaining_challenges(&pool, associate).await?;
+            let tagged = tagged_challenge(&sub_ext.caption, &challenges);
+            actions
+                .send_text(
+                    judge_chat,
+                    text,
+                    SendOptions {
+                        reply_to: forwarded_id.map(MessageId),
+                        thread_id,
+                        keyboard: Some(make_keyboard(
+                            associate as u64,
+                            message_id,
+                            challenges,
+                            tagged,
+                            judge_keyboard_columns(&pool).await?,
+                        )),
+                        silent: true,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            bot.send_message(msg.chat.id, "Judge keyboard posted again")
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::FetchSubmissionMedia { message_id } => {
+            #[derive(sqlx::FromRow)]
+            struct StoredMedia {
+                user: i64,
+                r#type: i32,
+                caption: String,
+                file_path: Option<String>,
+                thumbnail_path: Option<String>,
+                forwarded_message_id: Option<i32>,
+                judge_chat_id: Option<i64>,
+            }
+            let submission = sqlx::query_as::<_, StoredMedia>(
+                "SELECT user, type, caption, file_path, thumbnail_path, forwarded_message_id, judge_chat_id
+                FROM submissions WHERE message_id = $1",
+            )
+            .bind(message_id)
+            .fetch_optional(&pool)
+            .await?;
+            let Some(submission) = submission else {
+                bot.send_message(msg.chat.id, "Submission not found")
+                    .await?;
+                return Ok(());
+            };
+
+            // The full size file if it was kept, otherwise the thumbnail
+            let local = [&submission.file_path, &submission.thumbnail_path]
+                .into_iter()
+                .flatten()
+                .find(|path| Path::new(path).exists());
+            if let Some(path) = local {
+                let file = InputFile::file(Path::new(path));
+                match submission.r#type {
+                    1 => {
+                        bot.send_video(msg.chat.id, file)
+                            .caption(submission.caption)
+                            .await?;
+                    }
+                    _ => {
+                        bot.send_photo(msg.chat.id, file)
+                            .caption(submission.caption)
+                            .await?;
+                    }
+                }
+                return Ok(());
+            }
+
+            // Without a local copy, forward the participant's message or the copy in the
+            // judge chat, whichever still exists
+            let sources = [
+                Some((ChatId(submission.user), MessageId(message_id))),
+                submission.forwarded_message_id.map(|forwarded_id| {
+                    (
+                        cfg.stored_judge_chat(submission.judge_chat_id),
+                        MessageId(forwarded_id),
+                    )
+                }),
+            ];
+            for (chat_id, source_id) in sources.into_iter().flatten() {
+                match bot.forward_message(msg.chat.id, chat_id, source_id).await {
+                    Ok(_) => return Ok(()),
+                    Err(e) => log::warn!(
+                        "Could not forward submission {} from {}: {}",
+                        message_id,
+                        chat_id,
+                        e
+                    ),
+                }
+            }
+            bot.send_message(
+                msg.chat.id,
+                "The media of this submission is gone: there is no file on disk and the message was deleted",
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::TestSubmission { team } => {
+            let team = team.trim();
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM users WHERE team = $1)
+                OR EXISTS(SELECT 1 FROM teams WHERE name = $1)",
+            )
+            .bind(team)
+            .fetch_one(&pool)
+            .await?;
+            if !exists {
+                bot.send_message(msg.chat.id, format!("Team `{}` not found", team))
+                    .await?;
+                return Ok(());
+            }
+            let maintainer = sender(&msg)?;
+            let caption = "🧪 Test submission".to_owned();
+            let date = chrono::Utc::now()
+                .naive_utc()
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string();
+            // Real submissions use the positive id of the participant's message, test ones
+            // count down from -1 so they never collide
+            let message_id: i32 = sqlx::query_scalar(
+                "INSERT INTO submissions (message_id, user, team, date, caption, type, test)
+                SELECT MIN(COALESCE(MIN(message_id), 0), 0) - 1, $1, $2, $3, $4, 0, 1
+                FROM submissions
+                RETURNING message_id",
+            )
+            .bind(maintainer.id.0 as i64)
+            .bind(team)
+            .bind(&date)
+            .bind(&caption)
+            .fetch_one(&pool)
+            .await?;
+            let sub_ext = SubmissionExtended {
+                message_id: message_id.into(),
+                team: team.to_owned(),
+                username: maintainer.username.clone(),
+                first_name: maintainer.first_name.clone(),
+                last_name: maintainer.last_name.clone(),
+                date,
+                caption,
+                r#type: 0,
+                forum_id: None,
+            };
+            let (challenges, _) = team_open_and_locked_challenges(&pool, team).await?;
+            actions
+                .send_text(
+                    cfg.judge_chat_for(team),
+                    format!(
+                        "🧪 TEST, doesn't count for the team\n{}",
+                        submission_message(&sub_ext)
+                    ),
+                    SendOptions {
+                        keyboard: Some(make_keyboard(
+                            maintainer.id.0,
+                            message_id,
+                            challenges,
+                            None,
+                            judge_keyboard_columns(&pool).await?,
+                        )),
+                        silent: true,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            bot.send_message(
+                msg.chat.id,
+                format!("Test submission {} posted to the judge chat", message_id),
+            )
+            .await?;
+            Ok(())
+        }
+        MaintainerCommands::SelfTest => {
+            let stages = self_test(&pool, &cfg, sender(&msg)?.id).await;
+            let passed = stages.iter().all(|(_, result)| result.is_ok());
+            let report = stages
+                .into_iter()
+                .map(|(stage, result)| match result {
+                    Ok(detail) => format!("✅ {}: {}", stage, detail),
+                    Err(problem) => format!("❌ {}: {}", stage, problem),
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            let verdict = match passed {
+                true => "Self test passed",
+                false => "Self test FAILED",
+            };
+            bot.send_message(msg.chat.id, format!("{}\n\n{}", verdict, report))
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::Judge {
+            image_ref: submission_ref,
+            challenge: challenge_name,
+        } => {
+            // Retrieve the associate aka user who submitted the submission from the sql
+            let associate = sqlx::query_as::<_, User>(
+                "SELECT u.id, u.team, u.username, u.first_name, u.last_name
+                FROM submissions s
+                LEFT JOIN users u ON s.user = u.id
+                WHERE s.message_id = $1",
+            )
+            .bind(submission_ref)
+            .fetch_optional(&pool)
+            .await?;
+            // A number picks the challenge at that position of the submission's judge keyboard
+            let index = challenge_name.trim().parse::<usize>().ok();
+            let challenge = match (index, &associate) {
+                (Some(index), Some(user)) => {
+                    let caption: String =
+                        sqlx::query_scalar("SELECT caption FROM submissions WHERE message_id = $1")
+                            .bind(submission_ref)
+                            .fetch_one(&pool)
+                            .await?;
+                    let remaining = remaining_challenges(&pool, user.id).await?;
+                    let tagged = tagged_challenge(&caption, &remaining);
+                    let ordered = keyboard_order(remaining, tagged);
+                    match index.checked_sub(1).and_then(|i| ordered.get(i)) {
+                        Some(challenge) => Some(challenge.clone()),
+                        None => {
+                            let options = ordered
+                                .iter()
+                                .enumerate()
+                                .map(|(i, challenge)| format!("{}. {}", i + 1, challenge.label()))
+                                .collect::<Vec<String>>();
+                            let text = match options.is_empty() {
+                                true => "The team has no challenges left".to_owned(),
+                                false => format!(
+                                    "No challenge {} for this submission, choose one of:\n{}",
+                                    index,
+                                    options.join("\n")
+                                ),
+                            };
+                            for chunk in split_message(&text) {
+                                bot.send_message(msg.chat.id, chunk).await?;
+                            }
+                            return Ok(());
+                        }
+                    }
+                }
+                _ => None,
+            };
+            // Check that challenge exists
+            let challenge = match challenge.or_else(|| special_challenge(&challenge_name)) {
+                Some(challenge) => Some(challenge),
+                None => {
+                    sqlx::query_as::<_, Challenge>(
+                        "SELECT name, short_name, tiers
+                FROM challenges
+                WHERE name = $1",
+                    )
+                    .bind(challenge_name)
+                    .fetch_optional(&pool)
+                    .await?
+                }
+            };
+            match (associate, challenge) {
+                (Some(_), Some(challenge)) if !challenge.tier_points().is_empty() => {
+                    actions
+                        .send_text(
+                            msg.chat.id,
+                            pick_tier_text(&challenge),
+                            SendOptions::default(),
+                        )
+                        .await?;
+                }
+                (Some(user), Some(challenge)) => {
+                    let judged = judge(
+                        user.id.to_string(),
+                        submission_ref.to_string(),
+                        challenge.name,
+                        None,
+                        msg.from.as_ref().map(|judge| judge.id),
+                        &actions,
+                        &pool,
+                        &cfg,
+                    )
+                    .await;
+                    let text = match judged {
+                        Ok(()) => "Submission successfully judged",
+                        Err(BotError::SubmissionNotFound(_)) => "Submission not found",
+                        Err(e) => return Err(e),
+                    };
+                    bot.send_message(msg.chat.id, text).await?;
+                    refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+                }
+                (_, None) => {
+                    bot.send_message(msg.chat.id, "Challenge not found").await?;
+                }
+                (None, _) => {
+                    bot.send_message(msg.chat.id, "Submission not found")
+                        .await?;
+                }
+            }
+
+            Ok(())
+        }
+        MaintainerCommands::JudgeTeam {
+            team,
+            challenge,
+            from_id,
+            to_id,
+        } => {
+            let challenge = match special_challenge(&challenge) {
+                Some(challenge) => Some(challenge),
+                None => {
+                    sqlx::query_as::<_, Challenge>(
+                        "SELECT name, short_name FROM challenges WHERE name = $1",
+                    )
+                    .bind(challenge)
+                    .fetch_optional(&pool)
+                    .await?
+                }
+            };
+            let Some(challenge) = challenge else {
+                bot.send_message(msg.chat.id, "Challenge not found").await?;
+                return Ok(());
+            };
+            let submissions: Vec<(i64, i64, String, bool, bool, i32)> = sqlx::query_as(
+                "SELECT message_id, user, date, excluded, test, type FROM submissions
+                WHERE team = $1 AND message_id BETWEEN $2 AND $3
+                ORDER BY message_id",
+            )
+            .bind(&team)
+            .bind(from_id)
+            .bind(to_id)
+            .fetch_all(&pool)
+            .await?;
+            if submissions.is_empty() {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "Team `{}` has no submissions between {} and {}",
+                        team, from_id, to_id
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            if let Some(claimed_by) = claimed_by(&pool, &challenge.name, &team).await? {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "`{}` can only be won once and team `{}` already claimed it",
+                        challenge.name, claimed_by
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            // All or nothing, a half judged album is confusing to fix
+            let mut judged = vec![];
+            let mut first_valid = None;
+            let mut tx = pool.begin().await?;
+            for (message_id, user, date, excluded, test, media_type) in submissions {
+                let (mut points, mut valid) = judgement_points(
+                    &pool,
+                    &challenge.name,
+                    None,
+                    &date,
+                    excluded || test,
+                    media_type,
+                )
+                .await?;
+                // The challenge counts once, later shots of the same album only repeat it
+                let mut note = None;
+                if valid {
+                    match first_valid {
+                        Some(first) => {
+                            points = 0;
+                            valid = false;
+                            note = Some(format!("same challenge as {}", first));
+                        }
+                        None => first_valid = Some(message_id),
+                    }
+                }
+                sqlx::query(UPSERT_JUDGEMENT_QUERY)
+                    .bind(message_id)
+                    .bind(&challenge.name)
+                    .bind(points)
+                    .bind(valid)
+                    .bind(None::<i64>)
+                    .bind(msg.from.as_ref().map(|judge| judge.id.0 as i64))
+                    .bind(&note)
+                    .execute(&mut *tx)
+                    .await?;
+                judged.push((message_id, user, points, valid, note.is_some()));
+            }
+            tx.commit().await?;
+            award_podium_bonus(&pool, &challenge.name).await?;
+            // Notify with the points including a podium bonus
+            for (message_id, _, points, _, _) in judged.iter_mut() {
+                *points =
+                    sqlx::query_scalar("SELECT points FROM judgement WHERE submission_id = $1")
+                        .bind(*message_id)
+                        .fetch_one(&pool)
+                        .await?;
+            }
+
+            for (message_id, user, points, valid, _) in &judged {
+                let notified = notify_participant(
+                    &actions,
+                    &pool,
+                    &user.to_string(),
+                    &message_id.to_string(),
+                    &challenge.name,
+                    *points,
+                    *valid,
+                )
+                .await;
+                if let Err(e) = notified {
+                    log::warn!("Could not notify about submission {}: {}", message_id, e);
+                }
+            }
+            let valid = judged.iter().filter(|(_, _, _, valid, _)| *valid).count();
+            let duplicates = judged
+                .iter()
+                .filter(|(_, _, _, _, duplicate)| *duplicate)
+                .count();
+            if valid > 0 {
+                announce_first_claim(&actions, &pool, &challenge.name, &team, valid as i64).await?;
+            }
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Judged {} submission(s) of team `{}` as `{}`, {} valid, {} duplicate(s)",
+                    judged.len(),
+                    team,
+                    challenge.name,
+                    valid,
+                    duplicates
+                ),
+            )
+            .await?;
+            refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+            Ok(())
+        }
+        MaintainerCommands::SearchSubmissions { keyword } => {
+            let keyword = keyword.trim();
+            if keyword.is_empty() {
+                bot.send_message(msg.chat.id, "Please provide a keyword")
+                    .await?;
+                return Ok(());
+            }
+            let submissions = sqlx::query_as::<_, SubmissionExtended>(
+                "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id
+                FROM submissions s
+                LEFT JOIN users u ON s.user = u.id
+                WHERE s.caption LIKE '%' || $1 || '%'
+                ORDER BY s.date",
+            )
+            .bind(keyword)
+            .fetch_all(&pool)
+            .await?;
+            let text = format!(
+                "{} submission(s) matching {:?}:\n\n{}",
+                submissions.len(),
+                keyword,
+                submissions
+                    .iter()
+                    .map(submission_message)
+                    .collect::<Vec<String>>()
+                    .join("\n\n")
+            );
+            for chunk in split_message(&text) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            Ok(())
+        }
+        MaintainerCommands::ListSubmissions => {
+            let submissions = sqlx::query_as::<_, SubmissionExtended>("  
+                SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id
+                FROM submissions s
+                LEFT JOIN users u ON s.user = u.id").fetch_all(&pool).await?;
+            let submissions = submissions
+                .iter()
+                .map(submission_message)
+                .collect::<Vec<String>>()
+                .join("\n");
+            bot.send_message(msg.chat.id, format!("Submissions: {}", submissions))
+                .await?;
+            Ok(())
+        }
+        MaintainerCommands::ListFlaggedSubmissions => {
+            let submissions = sqlx::query_as::<_, SubmissionExtended>(
+                "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 AS forum_id
+                FROM submissions s
+                LEFT JOIN users u ON s.user = u.id
+                WHERE s.flagged
+                ORDER BY s.date",
+            )
+            .fetch_all(&pool)
+            .await?;
+            let text = if submissions.is_empty() {
+                "No flagged submissions".to_owned()
+            } else {
+                format!(
+                    "Flagged submissions, judge them with /judge:\n\n{}",
+                    submissions
+                        .iter()
+                        .map(submission_message)
+                        .collect::<Vec<String>>()
+                        .join("\n\n")
+                )
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::MessageUser { username, message } => {
+            let user_id: Option<i64> =
+                sqlx::query_scalar("SELECT id FROM users WHERE username = $1 COLLATE NOCASE")
+                    .bind(&username)
+                    .fetch_optional(&pool)
+                    .await?;
+            let Some(user_id) = user_id else {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("No participant with the username @{}", username),
+                )
+                .await?;
+                return Ok(());
+            };
+            let sent = bot
+                .send_message(
+                    UserId(user_id as u64),
+                    format!("📩 Message from the organizers:\n\n{}", message),
+                )
+                .await;
+            let text = match sent {
+                Ok(_) => format!("Message sent to @{}", username),
+                Err(RequestError::Api(
+                    ApiError::BotBlocked
+                    | ApiError::UserDeactivated
+                    | ApiError::CantInitiateConversation,
+                )) => format!("@{} blocked the bot or deleted their account", username),
+                Err(e) => return Err(e.into()),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+            Ok(())
+        }
+        MaintainerCommands::ListJudgements => {
+            let judgements = sqlx::query_as::<_, Judgement>("SELECT * FROM judgement")
+                .fetch_all(&pool)
+                .await?;
+            let points = points_format(&pool).await;
+            let judgements = judgements
+                .iter()
+                .map(|x| {
+                    format!(
+                        "- ref=`{}` challenge=`{}` {} valid={}",
+                        x.submission_id,
+                        x.challenge_name,
+                        format_points(x.points.into(), &points),
+                        x.valid
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            bot.send_message(msg.chat.id, format!("Judgements: {}", judgements))
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    pretty_env_logger::init();
+    let db_url: String = env::var("DATABASE_URL").expect("DATABASE_URL not set");
+    // Several judge chats share the load, e.g. JUDGE_CHAT_ID=-100123,-100456
+    let judge_chats: String = env::var("JUDGE_CHAT_ID").expect("JUDGE_CHAT_ID not set");
+    let judge_chats = judge_chats
+        .split(',')
+        .map(|x| ChatId(x.trim().parse::<i64>().unwrap()))
+        .collect::<Vec<ChatId>>();
+    let judge_chat = judge_chats[0];
+
+    let maintainers: String = env::var("MAINTAINERS").expect("MAINTAINERS not set");
+    let maintainers = maintainers
+        .split(",")
+        .map(|x| x.parse::<u64>().unwrap())
+        .map(UserId)
+        .collect::<HashSet<UserId>>();
+
+    let forum_chat = parse_recipient(
+        &env::var("FORUM_CHAT_ID").unwrap_or_else(|_| DEFAULT_FORUM_CHAT.to_owned()),
+    );
+
+    let submissions_dir = PathBuf::from(
+        env::var("SUBMISSIONS_DIR").unwrap_or_else(|_| DEFAULT_SUBMISSIONS_DIR.to_owned()),
+    );
+    std::fs::create_dir_all(&submissions_dir).expect("Failed to create SUBMISSIONS_DIR");
+    log::info!(
+        "Submissions are downloaded to {:?}",
+        std::fs::canonicalize(&submissions_dir).unwrap_or_else(|_| submissions_dir.clone())
+    );
+
+    let bot = Bot::from_env();
+    // Exercise the flows without sending anything to Telegram
+    let actions = if matches!(env::var("DRY_RUN").as_deref(), Ok("1") | Ok("true")) {
+        log::warn!("Dry run: submissions, judgements and forum topics are not sent to Telegram");
+        Actions::DryRun(DryRunBot::default())
+    } else {
+        Actions::Telegram(bot.clone())
+    };
+    let db = init_db(&db_url)
+        .await
+        .expect("Failed to initialize database");
+
+    // Fail fast: without the judge chats no submission can be forwarded
+    for &chat in &judge_chats {
+        if let Err(e) = actions
+            .send_text(
+                chat,
+                "🤖 Bot started".to_owned(),
+                SendOptions {
+                    silent: true,
+                    ..Default::default()
+                },
+            )
+            .await
+        {
+            log::error!(
+                "Cannot send messages to the judge chat {}: {}. Check JUDGE_CHAT_ID and that the bot is a member of that chat.",
+                chat,
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+
+    match check_config(&db).await {
+        Ok(report) if report.is_empty() => log::info!("Config OK"),
+        Ok(report) => {
+            for problem in report {
+                log::warn!("Config: {}", problem);
+            }
+        }
+        Err(e) => log::warn!("Could not check the config: {}", e),
+    }
+    match sqlx::query_as::<_, Challenge>("SELECT name, short_name, prerequisite FROM challenges")
+        .fetch_all(&db)
+        .await
+    {
+        Ok(challenges) => {
+            for challenge in prerequisites::cyclic(&challenges) {
+                log::warn!(
+                    "Prerequisite of {:?} is part of a cycle, ignoring it",
+                    challenge
+                );
+            }
+        }
+        Err(e) => log::warn!("Could not check challenge prerequisites: {}", e),
+    }
+    match event_start(&db).await {
+        Ok(Some(start)) => log::info!("Hunt starts at {} UTC", start),
+        Ok(None) => log::info!("No event_start configured, the hunt is running"),
+        Err(e) => log::warn!("Could not read event_start: {}", e),
+    }
+
+    // Team topics live in the forum chat, they only apply to the judge chat if both are the same
+    let judge_chat_is_forum = match bot.get_chat(forum_chat.clone()).await {
+        Ok(chat) => chat.id == judge_chat && is_forum(&chat),
+        Err(e) => {
+            log::warn!("Could not look up forum chat {:?}: {}", forum_chat, e);
+            false
+        }
+    };
+    log::info!(
+        "Forum chat {:?}, judge chats {:?} (first uses team topics: {})",
+        forum_chat,
+        judge_chats,
+        judge_chat_is_forum
+    );
+
+    let parameters = ConfigParameters {
+        judge_chat,
+        judge_chats,
+        maintainers,
+        forum_chat,
+        judge_chat_is_forum,
+        submissions_dir,
+    };
+
+    let lock = Arc::new(Mutex::new(()));
+    let submissions_enabled = Arc::new(AtomicBool::new(true));
+    let max_downloads = env::var("MAX_CONCURRENT_DOWNLOADS")
+        .map(|x| {
+            x.parse::<usize>()
+                .ok()
+                .filter(|&x| x > 0)
+                .expect("MAX_CONCURRENT_DOWNLOADS must be a positive number")
+        })
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+    let downloads = DownloadPermits(Arc::new(tokio::sync::Semaphore::new(max_downloads)));
+
+    // Teams that joined while the forum update failed get their topic eventually
+    let reconcile_interval = env::var("FORUM_RECONCILE_INTERVAL_SECS")
+        .map(|x| {
+            x.parse::<u64>()
+                .expect("FORUM_RECONCILE_INTERVAL_SECS must be a number")
+        })
+        .unwrap_or(DEFAULT_FORUM_RECONCILE_INTERVAL_SECS);
+    tokio::spawn({
+        let (actions, db, lock, forum_chat) = (
+            actions.clone(),
+            db.clone(),
+            lock.clone(),
+            parameters.forum_chat.clone(),
+        );
+        async move {
+            loop {
+                if let Err(e) = update_teams_in_forum(&actions, &db, &lock, &forum_chat).await {
+                    log::error!("Forum reconciliation failed: {}", e);
+                }
+                if reconcile_interval == 0 {
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(reconcile_interval)).await;
+            }
+        }
+    });
+
+    tokio::spawn(remind_of_schedule(actions.clone(), db.clone()));
+
+    let (queue, queued) = tokio::sync::mpsc::unbounded_channel();
+    let queue = JudgeQueue(queue);
+    match queue.requeue_unforwarded(&db).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("Queued {} submission(s) left over from the last run", count),
+        Err(e) => log::error!("Could not queue left over submissions: {}", e),
+    }
+    tokio::spawn(forward_submissions(
+        queued,
+        actions.clone(),
+        parameters.clone(),
+        db.clone(),
+    ));
+
+    let handler = Update::filter_message()
+        // Track activity once here instead of in every handler
+        .inspect_async(|msg: Message, pool: SqlitePool| async move {
+            if !msg.chat.is_private() {
+                return;
+            }
+            if let Err(e) =
+                sqlx::query("UPDATE users SET last_active = datetime('now') WHERE id = $1")
+                    .bind(msg.chat.id.0)
+                    .execute(&pool)
+                    .await
+            {
+                log::warn!("Could not update last activity of {}: {}", msg.chat.id, e);
+            }
+        })
+        .branch(
+            dptree::entry()
+                .filter_command::<ParticipantCommand>()
+                .filter(|msg: Message, cfg: ConfigParameters| {
+                    !(msg.chat.is_group() || msg.chat.is_supergroup())
+                        || cfg.is_judge_chat(msg.chat.id)
+                })
+                .branch(
+                    // Handle join team separately
+                    dptree::filter(|cmd: ParticipantCommand| {
+                        matches!(cmd, ParticipantCommand::JoinTeam(_))
+                    })
+                    .endpoint(join_team),
+                )
+                .branch(
+                    dptree::filter(|cmd: ParticipantCommand| {
+                        matches!(cmd, ParticipantCommand::TeamEmoji(_))
+                    })
+                    .endpoint(set_team_emoji),
+                )
+                .branch(
+                    dptree::entry()
+                        .filter_command::<ParticipantCommand>()
+                        .endpoint(participant_commands_handler),
+                ),
+        )
+        .branch(
+            // Filter a maintainer by a user ID
+            dptree::filter(|cfg: ConfigParameters, msg: Message| {
+                msg.from
+                    .map(|user| cfg.maintainers.contains(&user.id) && msg.chat.is_private())
+                    .unwrap_or_default()
+            })
+            .filter_command::<MaintainerCommands>()
+            .endpoint(maintainer_commands),
+        )
+        .branch(
+            // Maintainers set up challenges by sending a CSV or JSON document
+            dptree::filter_map(|cfg: ConfigParameters, msg: Message| {
+                let from = msg.from.as_ref()?;
+                if !cfg.maintainers.contains(&from.id) || !msg.chat.is_private() {
+                    return None;
+                }
+                let document = msg.document()?;
+                let format =
+                    challenge_import::Format::from_file_name(document.file_name.as_deref()?)?;
+                Some((document.file.id.clone(), format))
+            })
+            .endpoint(import_challenges),
+        )
+        .branch(
+            // Filter a media messages for submission
+            dptree::filter(|cfg: ConfigParameters, msg: Message| {
+                msg.chat.is_private()
+                    && !msg.chat.is_group()
+                    && !msg.chat.is_supergroup()
+                    && !cfg.is_judge_chat(msg.chat.id)
+            })
+            .filter_map(|msg: Message| match msg.kind {
+                MessageKind::Common(MessageCommon {
+                    media_kind: MediaKind::Photo(ref photos),
+                    ..
+                }) => Some(Media::Photo(photos.clone())),
+                MessageKind::Common(MessageCommon {
+                    media_kind: MediaKind::Video(ref video),
+                    ..
+                }) => Some(Media::Video(video.clone())),
+                _ => None,
+            })
+            .endpoint(receive_submission),
+        )
+        .branch(
+            // Participants reply to the request for a clear caption with a new one
+            dptree::filter_map_async(|msg: Message, pool: SqlitePool| async move {
+                let reply_to = msg.reply_to_message()?.id;
+                if !msg.chat.is_private() || msg.text().is_none() {
+                    return None;
+                }
+                sqlx::query_scalar::<_, i32>(
+                    "SELECT message_id FROM submissions
+                    WHERE clarification_message_id = $1 AND user = $2",
+                )
+                .bind(reply_to.0)
+                .bind(msg.chat.id.0)
+                .fetch_optional(&pool)
+                .await
+                .ok()
+                .flatten()
+                .map(|submission_id| ClarifiedSubmission(MessageId(submission_id)))
+            })
+            .endpoint(clarify_submission),
+        )
+        .branch(
+            // Judges reply to a forwarded submission with the challenge's short name
+            dptree::filter(|msg: Message, cfg: ConfigParameters| {
+                cfg.is_judge_chat(msg.chat.id)
+                    && msg.text().is_some()
+                    && msg.reply_to_message().is_some()
+            })
+            .endpoint(judge_by_reply),
+        )
+        .branch(
+            dptree::filter(|msg: Message, cfg: ConfigParameters| !cfg.is_judge_chat(msg.chat.id))
+                .endpoint(|bot: Bot, me: teloxide::types::Me, msg: Message, cfg: ConfigParameters, pool: SqlitePool| async move {
+                    if msg.chat.is_group() || msg.chat.is_supergroup() {
+                        // Media in a group is most likely a submission sent to the wrong chat
+                        let text = if msg.photo().is_some() || msg.video().is_some() {
+                            format!(
+                                "Looks like you wanted to submit this. Submissions only count if you send them to me directly: {}",
+                                me.tme_url()
+                            )
+                        } else {
+                            "Please use me in a private chat".to_owned()
+                        };
+                        bot.send_message(msg.chat.id, text)
+                            .reply_parameters(ReplyParameters::new(msg.id))
+                            .await?;
+                        return Ok(());
+                    }
+
+                    if let Some(text) = msg.text().filter(|text| text.starts_with('/')) {
+                        let is_maintainer = msg
+                            .from
+                            .as_ref()
+                            .is_some_and(|user| cfg.maintainers.contains(&user.id));
+                        let text = match suggest_command(text, is_maintainer) {
+                            Some(command) => format!("Did you mean {}?", command),
+                            None => "Unknown command. /help".to_owned(),
+                        };
+                        bot.send_message(msg.chat.id, text).await?;
+                        return Ok(());
+                    }
+
+                    let muted: bool = sqlx::query_scalar(
+                        "SELECT muted_chit_chat FROM user_preferences WHERE user_id = $1",
+                    )
+                    .bind(msg.chat.id.0)
+                    .fetch_optional(&pool)
+                    .await?
+                    .unwrap_or_default();
+                    if muted {
+                        return Ok(());
+                    }
+
+                    // Organizers can silence unrecognized text entirely
+                    let silent = config_value(&pool, "ignore_unrecognized_text")
+                        .await?
+                        .and_then(|x| x.parse::<bool>().ok())
+                        .unwrap_or_default();
+                    if silent && msg.text().is_some() {
+                        return Ok(());
+                    }
+                    let easter_eggs = config_value(&pool, "easter_eggs")
+                        .await?
+                        .and_then(|x| x.parse::<bool>().ok())
+                        .unwrap_or(true);
+
+                    if let Some(text) = msg.text().filter(|_| easter_eggs) {
+                        // Some easter eggs
+                        let response = match text.to_lowercase().as_str() {
+                            t if t.contains("beer") || t.contains("bier") => {
+                                "I love Bavarian beer!"
+                            }
+                            t if t.contains("prost") => "Prost!",
+                            t if t.contains("servus")
+                                || t.contains("hallo")
+                                || t.contains("hi")
+                                || t.contains("hey") =>
+                            {
+                                "Servus!"
+                            }
+                            _ => "Sorry, I didn't understand your message. /help",
+                        };
+                        bot.send_message(msg.chat.id, response).await?;
+                    } else if msg.text().is_some() {
+                        bot.send_message(msg.chat.id, "Sorry, I didn't understand your message. /help")
+                            .await?;
+                    } else {
+                        bot.send_message(
+                            msg.chat.id,
+                            "Sorry, this type of message isn't supported.",
+                        )
+                        .await?;
+                    }
+                    BotResult::Ok(())
+                }),
+        );
+
+    let meta_handler = dptree::entry()
+        .branch(handler)
+        .branch(Update::filter_callback_query().endpoint(callback_handler))
+        .branch(Update::filter_message_reaction_updated().endpoint(judge_by_reaction));
+
+    Dispatcher::builder(bot, meta_handler)
+        .dependencies(dptree::deps![
+            db,
+            parameters,
+            lock,
+            submissions_enabled,
+            actions.clone(),
+            queue,
+            downloads
+        ])
+        .default_handler(|upd| async move {
+            log::warn!("Unhandled update: {:?}", upd);
+        })
+        .error_handler(Arc::new(|e: BotError| async move {
+            log::error!(
+                "An error has occurred in the dispatcher ({}): {}",
+                e.category(),
+                e
+            );
+        }))
+        .enable_ctrlc_handler()
+        .build()
+        .dispatch()
+        .await;
+
+    if let Actions::DryRun(dry_run) = &actions {
+        log::info!("Dry run recorded {} call(s)", dry_run.recorded().len());
+    }
+    Ok(())
+}
+
+/// Challenges that have not yet been completed by the team of the user and are unlocked
+async fn remaining_challenges(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<Challenge>, sqlx::Error> {
+    Ok(open_and_locked_challenges(pool, user_id).await?.0)
+}
+
+/// Unlocked challenges not yet completed by the team a submission was made for, which can
+/// differ from the sender's team, e.g. for test submissions
+async fn submission_remaining_challenges(
+    pool: &SqlitePool,
+    submission_id: i32,
+) -> Result<Vec<Challenge>, sqlx::Error> {
+    let team: Option<String> =
+        sqlx::query_scalar("SELECT team FROM submissions WHERE message_id = $1")
+            .bind(submission_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(
+        team_open_and_locked_challenges(pool, &team.unwrap_or_default())
+            .await?
+            .0,
+    )
+}
+
+/// Challenges not yet completed by the team of the user, split into the unlocked ones and
+/// those still waiting for their prerequisite
+async fn open_and_locked_challenges(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<(Vec<Challenge>, Vec<Challenge>), sqlx::Error> {
+    let team: Option<String> = sqlx::query_scalar("SELECT team FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    team_open_and_locked_challenges(pool, &team.unwrap_or_default()).await
+}
+
+/// Challenges not yet completed by `team`, split into the unlocked ones and those still
+/// waiting for their prerequisite
+async fn team_open_and_locked_challenges(
+    pool: &SqlitePool,
+    team: &str,
+) -> Result<(Vec<Challenge>, Vec<Challenge>), sqlx::Error> {
+    let challenges = sqlx::query_as::<_, Challenge>(
+        "SELECT rowid AS id, name, short_name, emoji, \"desc\", prerequisite, tiers FROM challenges",
+    )
+    .fetch_all(pool)
+    .await?;
+    // Test submissions and invalid judgements complete nothing
+    let completed: HashSet<String> = sqlx::query_scalar(
+        "SELECT challenge_name
+        FROM judgement j
+        LEFT JOIN submissions s ON j.submission_id = s.message_id
+        WHERE s.team = $1 AND j.valid = 1 AND NOT s.test",
+    )
+    .bind(team)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let known = challenges
+        .iter()
+        .map(|challenge| challenge.name.as_str())
+        .collect::<HashSet<_>>();
+    let cyclic = prerequisites::cyclic(&challenges);
+    let (open, locked) = challenges
+        .iter()
+        .filter(|challenge| !completed.contains(&challenge.name))
+        .cloned()
+        .partition(|challenge| prerequisites::is_unlocked(challenge, &completed, &known, &cyclic));
+    Ok((open, locked))
+}
+
+/// Open challenges with their descriptions followed by the locked ones, `None` if there are
+/// none left
+fn open_challenges_text(open: &[Challenge], locked: &[Challenge]) -> Option<String> {
+    if open.is_empty() && locked.is_empty() {
+        return None;
+    }
+    let mut text = challenge_legend(open).replacen("Legend", "Open challenges", 1);
+    for challenge in locked {
+        text.push_str(&format!(
+            "\n🔒 locked — <b>{}</b>: complete {} first",
+            html::escape(&challenge.name),
+            html::escape(challenge.prerequisite.as_deref().unwrap_or_default())
+        ));
+    }
+    Some(text)
+}
+
+/// Full name and description of each challenge button, for judges new to the event
+fn challenge_legend(challenges: &[Challenge]) -> String {
+    let mut text = "<b>Legend</b>\n".to_owned();
+    for challenge in challenges {
+        text.push_str(&format!(
+            "\n{} — <b>{}</b>",
+            html::escape(&challenge.label()),
+            html::escape(&challenge.name)
+        ));
+        if let Some(desc) = challenge.desc.as_ref().filter(|desc| !desc.is_empty()) {
+            text.push_str(&format!(": {}", html::escape(desc)));
+        }
+    }
+    text
+}
+
+/// Short random team join code, without characters that are easily confused (0/O, 1/I)
+fn join_code() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut rng = rand::thread_rng();
+    (0..6)
+        .map(|_| *ALPHABET.choose(&mut rng).unwrap() as char)
+        .collect()
+}
+
+/// Closest known command to a mistyped `/command`, maintainer commands only for maintainers
+fn suggest_command(text: &str, is_maintainer: bool) -> Option<String> {
+    let name = text.strip_prefix('/')?.split_whitespace().next()?;
+    // Drop the bot mention of `/command@bot`
+    let name = name.split('@').next().unwrap_or(name).to_lowercase();
+    let mut commands = ParticipantCommand::bot_commands();
+    if is_maintainer {
+        commands.extend(MaintainerCommands::bot_commands());
+    }
+    commands
+        .into_iter()
+        .map(|command| {
+            let distance = strsim::levenshtein(&name, command.command.trim_start_matches('/'));
+            (distance, command.command)
+        })
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, command)| command)
+}
+
+/// Challenge the participant tagged in the caption, e.g. `#landmark` for the short name
+/// "landmark"; spaces and underscores are ignored since hashtags can't contain spaces
+fn tagged_challenge(caption: &str, challenges: &[Challenge]) -> Option<i64> {
+    let normalize = |x: &str| x.replace([' ', '_'], "").to_lowercase();
+    caption
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .find_map(|tag| {
+            let tag = normalize(tag.trim_end_matches(|c: char| c.is_ascii_punctuation()));
+            challenges
+                .iter()
+                .find(|challenge| normalize(&challenge.short_name) == tag)
+        })
+        .map(|challenge| challenge.id)
+}
+
+/// Allowed number of challenge buttons per row of the judge keyboard
+const JUDGE_KEYBOARD_COLUMNS: std::ops::RangeInclusive<usize> = 1..=4;
+
+/// Challenge buttons per row from `judge_keyboard_columns` in the `config` table, one per
+/// row by default
+async fn judge_keyboard_columns(pool: &SqlitePool) -> Result<usize, sqlx::Error> {
+    Ok(config_value(pool, "judge_keyboard_columns")
+        .await?
+        .and_then(|x| x.trim().parse::<usize>().ok())
+        .filter(|columns| JUDGE_KEYBOARD_COLUMNS.contains(columns))
+        .unwrap_or(1))
+}
+
+/// Challenges in the order of the judge keyboard, the one `tagged` by the participant first
+fn keyboard_order(mut challenges: Vec<Challenge>, tagged: Option<i64>) -> Vec<Challenge> {
+    // Stable sort keeps the order of the others
+    challenges.sort_by_key(|challenge| Some(challenge.id) != tagged);
+    challenges
+}
+
+/// Judging keyboard, the challenge `tagged` by the participant comes first and is marked
+fn make_keyboard(
+    associate: u64,
+    reference: i32,
+    challenges: Vec<Challenge>,
+    tagged: Option<i64>,
+    columns: usize,
+) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
+    let judge_data = |verdict| {
+        CallbackData::Judge {
+            associate,
+            submission: reference,
+            verdict,
+        }
+        .to_string()
+    };
+
+    for versions in keyboard_order(challenges, tagged).chunks(columns) {
+        let row = versions
+            .iter()
+            .map(|challenge| {
+                let label = if Some(challenge.id) == tagged {
+                    format!("✅ {} (tagged)", challenge.label())
+                } else {
+                    challenge.label()
+                };
+                InlineKeyboardButton::callback(label, judge_data(Verdict::Challenge(challenge.id)))
+            })
+            .collect();
+
+        keyboard.push(row);
+    }
+    keyboard.push(vec![
+        InlineKeyboardButton::callback(
+            "👤 Participant profile",
+            CallbackData::Profile { associate }.to_string(),
+        ),
+        InlineKeyboardButton::callback(
+            "ℹ️ Legend",
+            CallbackData::Legend {
+                associate,
+                submission: reference,
+            }
+            .to_string(),
+        ),
+    ]);
+    keyboard.push(vec![
+        InlineKeyboardButton::callback("⚠️ Unclear", judge_data(Verdict::Unclear)),
+        InlineKeyboardButton::callback("❌ Invalid", judge_data(Verdict::Invalid)),
+    ]);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "🏳️ Flag",
+        judge_data(Verdict::Flag),
+    )]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Quality tiers of `challenge`, one star per tier
+fn tier_keyboard(associate: u64, reference: i32, challenge: &Challenge) -> InlineKeyboardMarkup {
+    let tiers = challenge
+        .tier_points()
+        .into_iter()
+        .enumerate()
+        .map(|(i, points)| {
+            InlineKeyboardButton::callback(
+                format!("{} ({})", "⭐".repeat(i + 1), points),
+                CallbackData::Tier {
+                    associate,
+                    submission: reference,
+                    challenge: challenge.id,
+                    tier: i + 1,
+                }
+                .to_string(),
+            )
+        })
+        .collect();
+    InlineKeyboardMarkup::new(vec![
+        tiers,
+        vec![InlineKeyboardButton::callback(
+            "⬅️ Back",
+            CallbackData::Back {
+                associate,
+                submission: reference,
+            }
+            .to_string(),
+        )],
+    ])
+}
+
+/// Any team member can pick the team's emoji, there are no team captains
+async fn set_team_emoji(
+    bot: Bot,
+    actions: Actions,
+    msg: Message,
+    cmd: ParticipantCommand,
+    cfg: ConfigParameters,
+    lock: Arc<Mutex<()>>,
+    pool: SqlitePool,
+) -> BotResult {
+    let ParticipantCommand::TeamEmoji(emoji) = cmd else {
+        unreachable!()
+    };
+    if msg.chat.is_group() || msg.chat.is_supergroup() {
+        bot.send_message(msg.chat.id, "Please use me in a private chat")
+            .await?;
+        return Ok(());
+    }
+    let team: Option<String> = sqlx::query_scalar("SELECT team FROM users WHERE id = $1")
+        .bind(sender(&msg)?.id.0 as i64)
+        .fetch_optional(&pool)
+        .await?;
+    let Some(team) = team else {
+        bot.send_message(msg.chat.id, "You are not yet part of a team")
+            .await?;
+        return Ok(());
+    };
+    let emoji = emoji.trim();
+    if emojis::get(emoji).is_none() {
+        bot.send_message(
+            msg.chat.id,
+            "Please send a single emoji, e.g. /team_emoji 🦄",
+        )
+        .await?;
+        return Ok(());
+    }
+    sqlx::query(
+        "INSERT INTO teams (name, emoji, created_at) VALUES ($1, $2, datetime('now'))
+        ON CONFLICT(name) DO UPDATE SET emoji = excluded.emoji",
+    )
+    .bind(&team)
+    .bind(emoji)
+    .execute(&pool)
+    .await?;
+    bot.send_message(msg.chat.id, format!("Your team is now {} {}", emoji, team))
+        .await?;
+
+    update_teams_in_forum(&actions, &pool, &lock, &cfg.forum_chat).await?;
+    Ok(())
+}
+
+const MAX_TEAM_NAME_LENGTH: usize = 40;
+/// Words participants send when they don't know what to join yet, and the team of /self_test
+const RESERVED_TEAM_NAMES: &[&str] = &[
+    "help",
+    "team",
+    "join",
+    "join_team",
+    "start",
+    "none",
+    SELF_TEST_TEAM,
+];
+
+/// Why `name` can't be used for a new team, if anything
+fn invalid_team_name(name: &str) -> Option<&'static str> {
+    let name = name.trim();
+    if name.is_empty() {
+        Some("The team name is empty")
+    } else if name.starts_with('/') {
+        Some("That's a command, not a team name")
+    } else if name.chars().count() > MAX_TEAM_NAME_LENGTH {
+        Some("The team name is too long")
+    } else if RESERVED_TEAM_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+    {
+        Some("That word is reserved")
+    } else {
+        None
+    }
+}
+
+async fn join_team(
+    bot: Bot,
+    actions: Actions,
+    msg: Message,
+    cmd: ParticipantCommand,
+    cfg: ConfigParameters,
+    lock: Arc<Mutex<()>>,
+    pool: SqlitePool,
+) -> BotResult {
+    match cmd {
+        ParticipantCommand::JoinTeam(team) => {
+            if msg.chat.is_group() || msg.chat.is_supergroup() {
+                let text = if cfg.is_judge_chat(msg.chat.id) {
+                    "Teams can't be joined from the judge chat. Send /join_team to me in a private chat."
+                } else {
+                    "Please use me in a private chat"
+                };
+                actions
+                    .send_text(msg.chat.id, text.to_owned(), SendOptions::default())
+                    .await?;
+                return Ok(());
+            }
+            if team.trim().is_empty() {
+                bot.send_message(
+                    msg.chat.id,
+                    "Please provide a team name. /join_team followed by the team name",
+                )
+                .await?;
+                return Ok(());
+            }
+            // Join codes resolve to their team, plain team names keep working
+            let code_team: Option<String> =
+                sqlx::query_scalar("SELECT name FROM teams WHERE code = $1 COLLATE NOCASE")
+                    .bind(team.trim())
+                    .fetch_optional(&pool)
+                    .await?;
+            let team = match code_team {
+                Some(team) => team,
+                None => {
+                    let team = team.trim().to_owned();
+                    // Existing teams stay joinable, only new names are checked
+                    let exists: bool = sqlx::query_scalar(
+                        "SELECT EXISTS(SELECT 1 FROM users WHERE team = $1)
+                        OR EXISTS(SELECT 1 FROM teams WHERE name = $1)",
+                    )
+                    .bind(&team)
+                    .fetch_one(&pool)
+                    .await?;
+                    let is_username: bool = sqlx::query_scalar(
+                        "SELECT EXISTS(SELECT 1 FROM users WHERE username = $1 COLLATE NOCASE)",
+                    )
+                    .bind(team.trim_start_matches('@'))
+                    .fetch_one(&pool)
+                    .await?;
+                    let problem = if is_username
+                        || sender(&msg)?.username.as_deref().is_some_and(|username| {
+                            username.eq_ignore_ascii_case(team.trim_start_matches('@'))
+                        }) {
+                        Some("That's the username of a participant, not a team")
+                    } else {
+                        invalid_team_name(&team)
+                    };
+                    if let (false, Some(problem)) = (exists, problem) {
+                        bot.send_message(
+                            msg.chat.id,
+                            format!(
+                                "{}. Team names have at most {} characters and aren't commands or reserved words, e.g. /join_team Team Rocket",
+                                problem, MAX_TEAM_NAME_LENGTH
+                            ),
+                        )
+                        .await?;
+                        return Ok(());
+                    }
+                    team
+                }
+            };
+            let data = User {
+                id: sender(&msg)?.id.0 as i64,
+                team: team.to_owned(),
+                username: sender(&msg)?.username.clone(),
+                first_name: sender(&msg)?.first_name.clone(),
+                last_name: sender(&msg)?.last_name.clone(),
+            };
+            let mut tx = pool.begin().await?;
+            let previous_team: Option<String> =
+                sqlx::query_scalar("SELECT team FROM users WHERE id = $1")
+                    .bind(data.id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            sqlx::query(
+                "INSERT INTO users (id, team, username, first_name, last_name, created_at)
+                VALUES ($1, $2, $3, $4, $5, datetime('now'))
+                ON CONFLICT(id) DO UPDATE SET team = excluded.team",
+            )
+            .bind(data.id)
+            .bind(data.team.clone())
+            .bind(data.username)
+            .bind(data.first_name)
+            .bind(data.last_name)
+            .execute(&mut *tx)
+            .await?;
+            let mut excluded_submissions = 0;
+            let mut lost_podiums: Vec<String> = Vec::new();
+            if let Some(old_team) = previous_team.filter(|old_team| *old_team != data.team) {
+                sqlx::query(
+                    "INSERT INTO team_changes (user_id, old_team, new_team, changed_at)
+                    VALUES ($1, $2, $3, datetime('now'))",
+                )
+                .bind(data.id)
+                .bind(old_team.clone())
+                .bind(data.team)
+                .execute(&mut *tx)
+                .await?;
+                // Submissions made for the previous team count for neither team anymore
+                excluded_submissions = sqlx::query(
+                    "UPDATE submissions SET excluded = 1 WHERE user = $1 AND team = $2 AND NOT excluded",
+                )
+                .bind(data.id)
+                .bind(old_team)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+                lost_podiums = sqlx::query_scalar(
+                    "SELECT DISTINCT j.challenge_name FROM judgement j
+                    JOIN submissions s ON j.submission_id = s.message_id
+                    WHERE s.user = $1 AND s.excluded AND j.valid = 1",
+                )
+                .bind(data.id)
+                .fetch_all(&mut *tx)
+                .await?;
+                // The bonus goes with the points, the podiums are recomputed below
+                sqlx::query(
+                    "UPDATE judgement SET valid = 0, points = 0, bonus = 0, podium_place = NULL
+                    WHERE submission_id IN (SELECT message_id FROM submissions WHERE user = $1 AND excluded)",
+                )
+                .bind(data.id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+            for challenge in lost_podiums {
+                award_podium_bonus(&pool, &challenge).await?;
+            }
+            bot.send_message(msg.chat.id, format!("You joined team `{}`\n\nCheck the team members with /team\\_overview\\.\nDon't change your team \\(name\\) after the first submisssion; previous submissions will not count anymore", team))
+                .parse_mode(ParseMode::MarkdownV2)
+                .await?;
+            if excluded_submissions > 0 {
+                bot.send_message(
+                    msg.chat.id,
+                    format!("{excluded_submissions} submission(s) made for your previous team no longer count"),
+                )
+                .await?;
+            }
+
+            update_teams_in_forum(&actions, &pool, &lock, &cfg.forum_chat).await?;
+        }
+        _ => {
+            unreachable!()
+        }
+    };
+    Ok(())
+}
+
+/// Commands that act on the sender's team and are meaningless for judges without one
+fn is_team_command(cmd: &ParticipantCommand) -> bool {
+    matches!(
+        cmd,
+        ParticipantCommand::JoinTeam(_)
+            | ParticipantCommand::TeamOverview
+            | ParticipantCommand::TeamEmoji(_)
+            | ParticipantCommand::Score
+    )
+}
+
+async fn participant_commands_handler(
+    cfg: ConfigParameters,
+    bot: Bot,
+    actions: Actions,
+    me: teloxide::types::Me,
+    msg: Message,
+    cmd: ParticipantCommand,
+    pool: SqlitePool,
+) -> BotResult {
+    let in_judge_chat = cfg.is_judge_chat(msg.chat.id);
+    if in_judge_chat && is_team_command(&cmd) {
+        // Judges usually aren't part of a team, so team-scoped commands have nothing to report
+        let is_participant = sqlx::query("SELECT id FROM users WHERE id = $1")
+            .bind(sender(&msg)?.id.0 as i64)
+            .fetch_optional(&pool)
+            .await?
+            .is_some();
+        if !is_participant {
+            actions
+                .send_text(
+                    msg.chat.id,
+                    "This command refers to the sender's team and isn't available in the judge chat. Use /scoreboard or /list_team_submission_judgments in a private chat with me instead.".to_owned(),
+                    SendOptions::default(),
+                )
+                .await?;
+            return Ok(());
+        }
+    }
+    let allowed_in_group = matches!(cmd, ParticipantCommand::Help)
+        || (in_judge_chat
+            && !is_team_command(&cmd)
+            && !matches!(
+                cmd,
+                ParticipantCommand::Start
+                    | ParticipantCommand::WithdrawSubmission { .. }
+                    | ParticipantCommand::Feedback { .. }
+            ));
+    if (msg.chat.is_group() || msg.chat.is_supergroup()) && !allowed_in_group {
+        actions
+            .send_text(
+                msg.chat.id,
+                "Please use me in a private chat".to_owned(),
+                SendOptions::default(),
+            )
+            .await?;
+        return Ok(());
+    }
+    match cmd {
+        ParticipantCommand::Start => {
+            bot.send_message(
+                msg.chat.id,
+                format!("Hello {}", msg.chat.first_name().unwrap_or("Spree Breaker")),
+            )
+            .await?;
+            bot.send_message(
+                msg.chat.id,
+                "Check /help for ways that I can provide you help. To get started with the photo challenge use /join_team followed by the team name. The team name must be identical for all team members."
+            )
+            .await?;
+        }
+        ParticipantCommand::Help => {
+            let user = sender(&msg)?;
+            let language = user.language_code.as_deref();
+            let in_group = msg.chat.is_group() || msg.chat.is_supergroup();
+            let participant_help =
+                i18n::help_text::<ParticipantCommand>(language, in_group.then(|| me.username()))
+                    .unwrap_or_else(|| {
+                        if in_group {
+                            ParticipantCommand::descriptions()
+                                .username_from_me(&me)
+                                .to_string()
+                        } else {
+                            ParticipantCommand::descriptions().to_string()
+                        }
+                    });
+            let text = if cfg.maintainers.contains(&user.id) {
+                format!(
+                    "{}\n\n{}",
+                    participant_help,
+                    i18n::help_text::<MaintainerCommands>(language, None)
+                        .unwrap_or_else(|| MaintainerCommands::descriptions().to_string())
+                )
+            } else {
+                participant_help
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        ParticipantCommand::JoinTeam(_team) => {
+            unreachable!("This should be handled by the join_team function");
+        }
+        ParticipantCommand::TeamEmoji(_emoji) => {
+            unreachable!("This should be handled by the set_team_emoji function");
+        }
+        ParticipantCommand::Feedback { submission, text } => {
+            let user = sender(&msg)?;
+            // Only submissions of the own team can be disputed
+            let submission = match submission {
+                Some(submission) => {
+                    let found: Option<(i64, Option<i32>, Option<i64>)> = sqlx::query_as(
+                        "SELECT s.user, s.forwarded_message_id, s.judge_chat_id FROM submissions s
+                        JOIN users u ON u.team = s.team
+                        WHERE s.message_id = $1 AND u.id = $2",
+                    )
+                    .bind(submission)
+                    .bind(user.id.0 as i64)
+                    .fetch_optional(&pool)
+                    .await?;
+                    let Some((associate, forwarded_id, judge_chat_id)) = found else {
+                        bot.send_message(msg.chat.id, "Your team has no submission with this ID")
+                            .await?;
+                        return Ok(());
+                    };
+                    Some((submission, associate, forwarded_id, judge_chat_id))
+                }
+                None => None,
+            };
+            sqlx::query(
+                "INSERT INTO feedback (user_id, submission_id, text, created_at)
+                VALUES ($1, $2, $3, datetime('now'))",
+            )
+            .bind(user.id.0 as i64)
+            .bind(submission.map(|(id, _, _, _)| id))
+            .bind(&text)
+            .execute(&pool)
+            .await?;
+
+            // Next to the disputed submission, general feedback goes to the first judge chat
+            let judge_chat = cfg
+                .stored_judge_chat(submission.and_then(|(_, _, _, judge_chat_id)| judge_chat_id));
+            let mut feedback = bot.send_message(
+                judge_chat,
+                format!(
+                    "💬 Feedback from {}{}:\n\n{}",
+                    user.full_name(),
+                    submission
+                        .map(|(id, _, _, _)| format!(" about submission {}", id))
+                        .unwrap_or_default(),
+                    text
+                ),
+            );
+            if let Some((id, associate, forwarded_id, _)) = submission {
+                feedback.reply_parameters = forwarded_id.map(|forwarded_id| {
+                    ReplyParameters::new(MessageId(forwarded_id)).allow_sending_without_reply()
+                });
+                feedback = feedback.reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                    InlineKeyboardButton::callback(
+                        "⚖️ Judge again",
+                        CallbackData::Reopen {
+                            associate: associate as u64,
+                            submission: id,
+                        }
+                        .to_string(),
+                    ),
+                ]]));
+            }
+            feedback.await?;
+            bot.send_message(msg.chat.id, "Thanks, the organizers will look into it")
+                .await?;
+        }
+        ParticipantCommand::TimeLeft => {
+            let now = chrono::Utc::now().naive_utc();
+            let offset = event_utc_offset(&pool).await?;
+            let deadline = submission_deadline(&pool).await?;
+            let text = match event_start(&pool).await? {
+                Some(start) if now < start => format!(
+                    "The hunt hasn't started yet, it starts in {} ({})",
+                    calendar::countdown(now, start),
+                    calendar::format_local(start, offset)
+                ),
+                _ => match deadline {
+                    Some(deadline) if now < deadline => format!(
+                        "Submissions close in {} ({})",
+                        calendar::countdown(now, deadline),
+                        calendar::format_local(deadline, offset)
+                    ),
+                    Some(deadline) => format!(
+                        "Submissions closed at {}",
+                        calendar::format_local(deadline, offset)
+                    ),
+                    None => "Submissions are open, there is no deadline".to_owned(),
+                },
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        ParticipantCommand::Mute => {
+            let muted: bool = sqlx::query_scalar(
+                "INSERT INTO user_preferences (user_id, muted_chit_chat) VALUES ($1, 1)
+                ON CONFLICT(user_id) DO UPDATE SET muted_chit_chat = NOT muted_chit_chat
+                RETURNING muted_chit_chat",
+            )
+            .bind(sender(&msg)?.id.0 as i64)
+            .fetch_one(&pool)
+            .await?;
+            let text = if muted {
+                "I'll stay quiet on casual messages. Send /mute again to undo."
+            } else {
+                "I'll answer casual messages again."
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        ParticipantCommand::MuteResults => {
+            let muted: bool = sqlx::query_scalar(
+                "INSERT INTO user_preferences (user_id, muted_results) VALUES ($1, 1)
+                ON CONFLICT(user_id) DO UPDATE SET muted_results = NOT muted_results
+                RETURNING muted_results",
+            )
+            .bind(sender(&msg)?.id.0 as i64)
+            .fetch_one(&pool)
+            .await?;
+            let text = if muted {
+                "I'll only react to your judged submissions. Send /mute_results again to undo."
+            } else {
+                "I'll message you again when a submission completes a challenge."
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        ParticipantCommand::TeamOverview => {
+            let team_members = sqlx::query_as::<_, User>(
+                "SELECT * FROM users WHERE team = (SELECT team FROM users WHERE id = $1)",
+            )
+            .bind(sender(&msg)?.id.0 as i64)
+            .fetch_all(&pool)
+            .await?;
+
+            let team = sqlx::query_as::<_, Team>(
+                "SELECT team, COUNT(*) AS count FROM users WHERE id = $1 LIMIT 1",
+            )
+            .bind(sender(&msg)?.id.0 as i64)
+            .fetch_one(&pool)
+            .await?;
+
+            if team.count > 0 {
+                let team_members_text = if team_members.is_empty() {
+                    "No team members yet".to_owned()
+                } else {
+                    team_members
+                        .iter()
+                        .map(|x| format!("- {}", x))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                };
+                log::warn!("{:?}", team);
+                let emoji = team_emojis(&pool)
+                    .await?
+                    .remove(&team.team)
+                    .map(|emoji| format!("{} ", emoji))
+                    .unwrap_or_default();
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "Overview team {}<code>{}</code>\n\n{} Member(s):\n{team_members_text}",
+                        emoji,
+                        html::escape(&team.team),
+                        team_members.len()
+                    ),
+                )
+                .parse_mode(ParseMode::Html)
+                .await?;
+            } else {
+                bot.send_message(msg.chat.id, "You are not yet part of a team")
+                    .await?;
+            }
+        }
+        ParticipantCommand::Challenges => {
+            let now = chrono::Utc::now().naive_utc();
+            if let Some(start) = event_start(&pool).await?.filter(|start| now < *start) {
+                bot.send_message(
+                    msg.chat.id,
+                    format!(
+                        "The hunt starts in {} ({})",
+                        calendar::countdown(now, start),
+                        calendar::format_local(start, event_utc_offset(&pool).await?)
+                    ),
+                )
+                .await?;
+                return Ok(());
+            }
+            let user_id = sender(&msg)?.id.0 as i64;
+            let (open, locked) = open_and_locked_challenges(&pool, user_id).await?;
+            let text = open_challenges_text(&open, &locked)
+                .unwrap_or_else(|| "Your team completed all challenges 🎉".to_owned());
+            bot.send_message(msg.chat.id, text)
+                .parse_mode(ParseMode::Html)
+                .await?;
+        }
+        ParticipantCommand::Score => {
+            let user_id = sender(&msg)?.id.0 as i64;
+            #[derive(sqlx::FromRow, Debug)]
+            struct ChallengeExtended {
+                challenge_name: String,
+                emoji: Option<String>,
+                points: i32,
+                tier: Option<i64>,
+                podium_place: Option<i64>,
+                bonus: i32,
+            }
+            // Join over the tables users, submissions and judgement for the user_id
+            let res = sqlx::query_as::<_, ChallengeExtended>(
+                "SELECT j.challenge_name, c.emoji, j.points, j.tier, j.podium_place, j.bonus
+                FROM judgement j
+                LEFT JOIN submissions s ON j.submission_id = s.message_id
+                LEFT JOIN users u ON s.team = u.team
+                LEFT JOIN challenges c ON j.challenge_name = c.name
+                WHERE u.id = $1 AND j.valid = 1",
+            )
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await?;
+            let points = points_format(&pool).await;
+            let scores = res
+                .into_iter()
+                .map(|x| {
+                    let mut change = format_points_change(x.points.into(), &points);
+                    if let Some(tier) = x.tier.filter(|tier| *tier > 0) {
+                        change.push_str(&format!(" {}", "⭐".repeat(tier as usize)));
+                    }
+                    if let Some(place) = x.podium_place {
+                        change.push_str(&format!(
+                            " ({} + {} {} bonus)",
+                            format_points((x.points - x.bonus).into(), &points),
+                            podium_medal(place),
+                            format_points(x.bonus.into(), &points)
+                        ));
+                    }
+                    match x.emoji.filter(|emoji| !emoji.is_empty()) {
+                        Some(emoji) => format!("- {} {} {}", emoji, x.challenge_name, change),
+                        None => format!("- {} {}", x.challenge_name, change),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            #[derive(sqlx::FromRow, Debug)]
+            struct Score {
+                score: i32,
+            }
+            let team: Option<String> = sqlx::query_scalar("SELECT team FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(&pool)
+                .await?;
+            let team = team.unwrap_or_default();
+            let adjustments = sqlx::query_as::<_, Adjustment>(
+                "SELECT points, reason, created_at FROM adjustments WHERE team = $1",
+            )
+            .bind(team.clone())
+            .fetch_all(&pool)
+            .await?;
+            let adjustments = adjustments
+                .iter()
+                .map(|x| {
+                    format!(
+                        "\n- {} {}",
+                        x.reason,
+                        format_points_change(x.points.into(), &points)
+                    )
+                })
+                .collect::<String>();
+            let total = format_points(team_score(&pool, &team).await?, &points);
+            // Get the number of submissions of the team of the current user and how many of them appear in the table judgement
+            let res_submissions = sqlx::query_as::<_, Score>(
+                "SELECT COUNT(*) as score
+                    FROM submissions s
+                    LEFT JOIN users u ON s.team = u.team
+                    WHERE u.id = $1",
+            )
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await?;
+            let ranking = sqlx::query_as::<_, TeamScore>(TEAM_SCORES_QUERY)
+                .fetch_all(&pool)
+                .await?;
+            let gap = score_gap(&ranking, &team, &points);
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "{scores}{adjustments}\n\nTotal score from {} submissions: {}\n{}",
+                    res_submissions.score, total, gap
+                ),
+            )
+            .await?;
+        }
+        ParticipantCommand::WithdrawSubmission { message_id } => {
+            #[derive(sqlx::FromRow)]
+            struct Withdrawn {
+                forwarded_message_id: Option<i32>,
+                judge_chat_id: Option<i64>,
+                file_path: Option<String>,
+                thumbnail_path: Option<String>,
+            }
+            let submission: Option<Withdrawn> = sqlx::query_as(
+                "SELECT forwarded_message_id, judge_chat_id, file_path, thumbnail_path FROM submissions
+                WHERE message_id = $1 AND user = $2",
+            )
+            .bind(message_id)
+            .bind(sender(&msg)?.id.0 as i64)
+            .fetch_optional(&pool)
+            .await?;
+            let Some(Withdrawn {
+                forwarded_message_id,
+                judge_chat_id,
+                file_path,
+                thumbnail_path,
+            }) = submission
+            else {
+                bot.send_message(msg.chat.id, "You have no submission with this ID")
+                    .await?;
+                return Ok(());
+            };
+            let judged_valid: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM judgement WHERE submission_id = $1 AND valid = 1)",
+            )
+            .bind(message_id)
+            .fetch_one(&pool)
+            .await?;
+            if judged_valid {
+                bot.send_message(
+                    msg.chat.id,
+                    "This submission was already judged and can't be withdrawn anymore",
+                )
+                .await?;
+                return Ok(());
+            }
+
+            let mut tx = pool.begin().await?;
+            sqlx::query("DELETE FROM judgement WHERE submission_id = $1")
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM submissions WHERE message_id = $1")
+                .bind(message_id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+
+            // Judges can't act on it anymore, the keyboard reports it as gone
+            if let Some(forwarded_message_id) = forwarded_message_id {
+                if let Err(e) = bot
+                    .delete_message(
+                        cfg.stored_judge_chat(judge_chat_id),
+                        MessageId(forwarded_message_id),
+                    )
+                    .await
+                {
+                    log::warn!(
+                        "Could not delete withdrawn submission {}: {}",
+                        message_id,
+                        e
+                    );
+                }
+            }
+            for file_path in file_path.into_iter().chain(thumbnail_path) {
+                if let Err(e) = fs::remove_file(&file_path).await {
+                    log::warn!("Could not remove {:?}: {}", file_path, e);
+                }
+            }
+            bot.send_message(msg.chat.id, "Your submission was withdrawn")
+                .await?;
+        }
+        ParticipantCommand::Schedule => {
+            let file = asset_file(&pool, "schedule_source", DEFAULT_SCHEDULE_SOURCE).await?;
+            bot.send_photo(msg.chat.id, file).await?;
+        }
+        ParticipantCommand::SurvivalGuide => {
+            let file = asset_file(&pool, "city_guide", DEFAULT_SURVIVAL_GUIDE_SOURCE).await?;
+            bot.send_document(msg.chat.id, file).await?;
+        }
+        ParticipantCommand::Rules => {
+            let rules = config_value(&pool, "rules")
+                .await?
+                .filter(|rules| !rules.trim().is_empty());
+            match rules {
+                Some(rules) => {
+                    let sent = bot
+                        .send_message(msg.chat.id, &rules)
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .await;
+                    // Unescaped characters break MarkdownV2, the rules are still readable
+                    // without formatting
+                    if let Err(RequestError::Api(e)) = sent {
+                        log::warn!("The configured rules aren't valid MarkdownV2: {}", e);
+                        bot.send_message(msg.chat.id, rules).await?;
+                    } else {
+                        sent?;
+                    }
+                }
+                None => {
+                    bot.send_message(msg.chat.id, default_rules())
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .await?;
+                }
+            }
+        }
+        ParticipantCommand::EmergencyInformation => {
+            #[derive(sqlx::FromRow, Debug)]
+            struct SafetyTeam {
+                name: String,
+                phone: String,
+            }
+            // Safety team shifts last until the morning
+            let current_date = calendar::event_day(
+                chrono::Utc::now().naive_utc(),
+                calendar::DEFAULT_ROLLOVER_HOUR,
+            )
+            .format("%Y-%m-%d")
+            .to_string();
+            log::trace!("Current date = {:?}", current_date);
+
+            let team = sqlx::query_as::<_, SafetyTeam>(
+                "SELECT name, phone FROM safety_team WHERE date = $1",
+            )
+            .bind(current_date)
+            .fetch_all(&pool)
+            .await?;
+            log::trace!("Safety team = {:?}", team);
+            let team_list = if team.is_empty() {
+                "No safety team available right now".to_owned()
+            } else {
+                team.iter()
+                    .map(|x| format!("{}: {}", x.name, x.phone))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            };
+            bot.send_message(msg.chat.id, format!("Our safety team right now. Do not hesitate to talk to any other tutors.\n{team_list}\n\n🚑 <b>Fire brigade & ambulance: +112</b>\n👮 Police: +110")).parse_mode(ParseMode::Html).await?;
+        }
+    };
+    Ok(())
+}
+
+/// Rules shown by /rules unless `rules` is set in the `config` table (as MarkdownV2)
+fn default_rules() -> String {
+    let section = |title: &str, lines: &[&str]| {
+        format!(
+            "{}\n{}",
+            markdown::bold(&markdown::escape(title)),
+            lines
+                .iter()
+                .map(|line| format!("• {}", markdown::escape(line)))
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    };
+    [
+        section(
+            "Scoring",
+            &[
+                "Send photos or videos of completed challenges to me directly, with a caption naming the challenge.",
+                "Each challenge counts once per team. Judges decide whether a submission is valid.",
+                "Use /score to see your points and /challenges for what's left.",
+            ],
+        ),
+        section(
+            "Teams",
+            &[
+                "Join a team with /join_team. Submissions stay with the team you were in when you sent them.",
+                "Switching teams is possible, but ask the organizers before you do.",
+            ],
+        ),
+        section(
+            "Conduct",
+            &[
+                "Be respectful to each other, locals and the places you visit.",
+                "No challenge is worth getting hurt or breaking the law. In an emergency use /emergency_information.",
+            ],
+        ),
+    ]
+    .join("\n\n")
+}
+
+/// Formatting of broadcasts from `broadcast_parse_mode` in the `config` table ("HTML" or
+/// "MarkdownV2"), plain text otherwise
+async fn broadcast_parse_mode(pool: &SqlitePool) -> Option<ParseMode> {
+    match config_value(pool, "broadcast_parse_mode").await {
+        Ok(Some(mode)) => match mode.to_lowercase().as_str() {
+            "html" => Some(ParseMode::Html),
+            "markdownv2" | "markdown" => Some(ParseMode::MarkdownV2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A broadcast message as participants receive it, shared with the preview
+fn broadcast_message(
+    bot: &Bot,
+    chat_id: impl Into<Recipient>,
+    message: &str,
+    parse_mode: Option<ParseMode>,
+) -> <Bot as Requester>::SendMessage {
+    let mut request = bot.send_message(chat_id, message);
+    request.parse_mode = parse_mode;
+    request
+}
+
+/// Send `message` to each user except the sender and return the number of delivered and
+/// failed messages; maintainers are told who the broadcast is from
+async fn broadcast(
+    bot: &Bot,
+    cfg: &ConfigParameters,
+    sender: &teloxide::types::User,
+    users: Vec<User>,
+    message: &str,
+    parse_mode: Option<ParseMode>,
+) -> (usize, usize) {
+    let (mut delivered, mut failed) = (0, 0);
+    for user in users {
+        if sender.id.0 == user.id as u64 {
+            continue;
+        }
+        let chat_id = UserId(user.id as u64);
+        let result = async {
+            if cfg.maintainers.contains(&chat_id) {
+                bot.send_message(chat_id, format!("Broadcast from {}", sender.full_name()))
+                    .await?;
+            }
+            broadcast_message(bot, chat_id, message, parse_mode).await?;
+            Ok::<_, teloxide::RequestError>(())
+        }
+        .await;
+        // Users might have blocked the bot, don't let them stop the broadcast
+        match result {
+            Ok(()) => delivered += 1,
+            Err(e) => {
+                log::warn!("Broadcast to {} failed: {}", user, e);
+                failed += 1;
+            }
+        }
+    }
+    (delivered, failed)
+}
+
+/// Upsert the challenges of a document sent by a maintainer. Columns left empty keep their
+/// current value for existing challenges.
+async fn import_challenges(
+    bot: Bot,
+    msg: Message,
+    pool: SqlitePool,
+    (file_id, format): (String, challenge_import::Format),
+) -> BotResult {
+    let file = bot.get_file(file_id).await?;
+    let mut content = vec![];
+    bot.download_file(&file.path, &mut content).await?;
+    let challenges = match challenge_import::parse(format, &content) {
+        Ok(challenges) => challenges,
+        Err(errors) => {
+            let text = format!(
+                "Nothing imported, the document has {} error(s):\n{}",
+                errors.len(),
+                errors.join("\n")
+            );
+            for chunk in split_message(&text) {
+                bot.send_message(msg.chat.id, chunk).await?;
+            }
+            return Ok(());
+        }
+    };
+
+    let mut inserted = 0;
+    let mut updated = 0;
+    let mut tx = pool.begin().await?;
+    for challenge in &challenges {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM challenges WHERE name = $1)")
+                .bind(&challenge.name)
+                .fetch_one(&mut *tx)
+                .await?;
+        sqlx::query(
+            "INSERT INTO challenges (name, short_name, desc, points, starts_at, decay_half_life, decay_floor, emoji, tiers, prerequisite, first_only, forum_thread_id, podium_bonus)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, COALESCE($11, 0), $12, $13)
+            ON CONFLICT(name) DO UPDATE SET
+                short_name = excluded.short_name,
+                desc = COALESCE(excluded.desc, desc),
+                points = COALESCE(excluded.points, points),
+                starts_at = COALESCE(excluded.starts_at, starts_at),
+                decay_half_life = COALESCE(excluded.decay_half_life, decay_half_life),
+                decay_floor = COALESCE(excluded.decay_floor, decay_floor),
+                emoji = COALESCE(excluded.emoji, emoji),
+                tiers = COALESCE(excluded.tiers, tiers),
+                prerequisite = COALESCE(excluded.prerequisite, prerequisite),
+                first_only = COALESCE($11, first_only),
+                forum_thread_id = COALESCE(excluded.forum_thread_id, forum_thread_id),
+                podium_bonus = COALESCE(excluded.podium_bonus, podium_bonus)",
+        )
+        .bind(&challenge.name)
+        .bind(&challenge.short_name)
+        .bind(&challenge.desc)
+        .bind(challenge.points)
+        .bind(&challenge.starts_at)
+        .bind(challenge.decay_half_life)
+        .bind(challenge.decay_floor)
+        .bind(&challenge.emoji)
+        .bind(&challenge.tiers)
+        .bind(&challenge.prerequisite)
+        .bind(challenge.first_only)
+        .bind(challenge.forum_thread_id)
+        .bind(&challenge.podium_bonus)
+        .execute(&mut *tx)
+        .await?;
+        if exists {
+            updated += 1;
+        } else {
+            inserted += 1;
+        }
+    }
+    tx.commit().await?;
+
+    log::info!(
+        "Imported challenges: {} inserted, {} updated",
+        inserted,
+        updated
+    );
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "Imported {} challenge(s): {} new, {} updated",
+            challenges.len(),
+            inserted,
+            updated
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Send a participant's team, score and all of their submissions to `chat_id`
+async fn send_participant_profile(
+    bot: &Bot,
+    pool: &SqlitePool,
+    chat_id: ChatId,
+    user_id: i64,
+) -> BotResult {
+    let Some(user) = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?
+    else {
+        bot.send_message(chat_id, "Participant not found").await?;
+        return Ok(());
+    };
+    let submissions = sqlx::query_as::<_, SubmissionExtended>(
+        "SELECT s.message_id, s.team, u.username, u.first_name, u.last_name, s.date, s.caption, s.type AS type, 0 as forum_id
+        FROM submissions s
+        LEFT JOIN users u ON s.user = u.id
+        WHERE s.user = $1
+        ORDER BY s.date",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    let team_score = format_points(
+        team_score(pool, &user.team).await?,
+        &points_format(pool).await,
+    );
+
+    let submissions_text = if submissions.is_empty() {
+        "No submissions yet".to_owned()
+    } else {
+        submissions
+            .iter()
+            .map(submission_message)
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    };
+    let text = format!(
+        "Profile of {} (#{})\nTeam: {} with {}\n{} submission(s) by this participant:\n\n{}",
+        user,
+        user.id,
+        user.team,
+        team_score,
+        submissions.len(),
+        submissions_text
+    );
+    for chunk in split_message(&text) {
+        bot.send_message(chat_id, chunk).await?;
+    }
+    Ok(())
+}
+
+/// Refusal for judging a challenge with tiers without picking one, which would silently
+/// award its base points
+fn pick_tier_text(challenge: &Challenge) -> String {
+    format!(
+        "'{}' has quality tiers, use the keyboard to pick a tier",
+        challenge.name
+    )
+}
+
+async fn judge_by_reply(
+    bot: Bot,
+    actions: Actions,
+    msg: Message,
+    cfg: ConfigParameters,
+    pool: SqlitePool,
+) -> BotResult {
+    let (Some(reply_to), Some(text)) = (msg.reply_to_message(), msg.text()) else {
+        return Ok(());
+    };
+    let submission: Option<(i64, i64)> = sqlx::query_as(
+        FORWARDED_SUBMISSION_QUERY
+            .replace("{columns}", "message_id, user")
+            .as_str(),
+    )
+    .bind(reply_to.id.0)
+    .bind(msg.chat.id.0)
+    .bind(cfg.judge_chat.0)
+    .fetch_optional(&pool)
+    .await?;
+    // Judges also reply to each other, only react to replies to forwarded submissions
+    let Some((submission_ref, associate)) = submission else {
+        return Ok(());
+    };
+
+    let choice = text.trim();
+    let challenge = match choice.to_lowercase().as_str() {
+        "unclear" => special_challenge("___unclear"),
+        "invalid" => special_challenge("___invalid"),
+        _ => {
+            sqlx::query_as::<_, Challenge>(
+                "SELECT rowid AS id, * FROM challenges
+                WHERE short_name = $1 COLLATE NOCASE OR name = $1 COLLATE NOCASE
+                LIMIT 1",
+            )
+            .bind(choice)
+            .fetch_optional(&pool)
+            .await?
+        }
+    };
+    let Some(challenge) = challenge else {
+        let short_names: Vec<String> = sqlx::query_scalar("SELECT short_name FROM challenges")
+            .fetch_all(&pool)
+            .await?;
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "Unknown challenge {:?}. Reply with one of: {}, unclear, invalid",
+                choice,
+                short_names.join(", ")
+            ),
+        )
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+        return Ok(());
+    };
+    if !challenge.tier_points().is_empty() {
+        actions
+            .send_text(
+                msg.chat.id,
+                pick_tier_text(&challenge),
+                SendOptions {
+                    reply_to: Some(msg.id),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        return Ok(());
+    }
+
+    judge(
+        associate.to_string(),
+        submission_ref.to_string(),
+        challenge.name.clone(),
+        None,
+        msg.from.as_ref().map(|judge| judge.id),
+        &actions,
+        &pool,
+        &cfg,
+    )
+    .await?;
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "Decision <b>{}</b>\n\nOverwrite with '/judge {} [challenge]'",
+            challenge.name, submission_ref
+        ),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_parameters(ReplyParameters::new(msg.id))
+    .await?;
+    refresh_pinned_scoreboard(&bot, &pool, msg.chat.id).await;
+    log::info!("Judge replied: {}", challenge.name);
+    Ok(())
+}
+
+/// Judge by reacting to a forwarded submission in the judge chat: ❤ accepts it as the
+/// tagged or first remaining challenge (unless that one needs a tier picked), ❌ (or 👎, since bots only see reactions from
+/// Telegram's default set) marks it invalid
+async fn judge_by_reaction(
+    bot: Bot,
+    actions: Actions,
+    cfg: ConfigParameters,
+    pool: SqlitePool,
+    reaction: MessageReactionUpdated,
+) -> BotResult {
+    let Some(user) = reaction.user.as_ref() else {
+        return Ok(());
+    };
+    if !cfg.is_judge_chat(reaction.chat.id) || !cfg.maintainers.contains(&user.id) {
+        return Ok(());
+    }
+    let accept = reaction
+        .new_reaction
+        .iter()
+        .any(|x| matches!(x, ReactionType::Emoji { emoji } if emoji == "❤" || emoji == "❤️"));
+    let reject = reaction
+        .new_reaction
+        .iter()
+        .any(|x| matches!(x, ReactionType::Emoji { emoji } if emoji == "❌" || emoji == "👎"));
+    if !accept && !reject {
+        return Ok(());
+    }
+    let submission: Option<(i64, i64, String)> = sqlx::query_as(
+        FORWARDED_SUBMISSION_QUERY
+            .replace("{columns}", "message_id, user, caption")
+            .as_str(),
+    )
+    .bind(reaction.message_id.0)
+    .bind(reaction.chat.id.0)
+    .bind(cfg.judge_chat.0)
+    .fetch_optional(&pool)
+    .await?;
+    let Some((submission_ref, associate, caption)) = submission else {
+        return Ok(());
+    };
+
+    let challenge = if reject {
+        special_challenge("___invalid")
+    } else {
+        let challenges = remaining_challenges(&pool, associate).await?;
+        let tagged = tagged_challenge(&caption, &challenges);
+        challenges
+            .into_iter()
+            .min_by_key(|challenge| Some(challenge.id) != tagged)
+    };
+    let Some(challenge) = challenge else {
+        bot.send_message(
+            reaction.chat.id,
+            "The team has no challenges left, use /judge instead",
+        )
+        .reply_parameters(ReplyParameters::new(reaction.message_id))
+        .await?;
+        return Ok(());
+    };
+    if !challenge.tier_points().is_empty() {
+        actions
+            .send_text(
+                reaction.chat.id,
+                pick_tier_text(&challenge),
+                SendOptions {
+                    reply_to: Some(reaction.message_id),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        return Ok(());
+    }
+
+    judge(
+        associate.to_string(),
+        submission_ref.to_string(),
+        challenge.name.clone(),
+        None,
+        Some(user.id),
+        &actions,
+        &pool,
+        &cfg,
+    )
+    .await?;
+    bot.send_message(
+        reaction.chat.id,
+        format!(
+            "Decision <b>{}</b> by reaction\n\nOverwrite with '/judge {} [challenge]'",
+            html::escape(&challenge.name),
+            submission_ref
+        ),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_parameters(ReplyParameters::new(reaction.message_id))
+    .await?;
+    refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+    log::info!("Judge reacted: {}", challenge.name);
+    Ok(())
+}
+
+async fn callback_handler(
+    bot: Bot,
+    actions: Actions,
+    cfg: ConfigParameters,
+    pool: SqlitePool,
+    q: CallbackQuery,
+) -> BotResult {
+    let Some(raw_data) = q.data else {
+        return Ok(());
+    };
+    let data = match raw_data.parse::<CallbackData>() {
+        Ok(data) => data,
+        Err(e) => {
+            log::error!("{}", e);
+            bot.answer_callback_query(q.id)
+                .text("This button is outdated or broken. Use /judge instead.")
+                .show_alert(true)
+                .await?;
+            return Ok(());
+        }
+    };
+    log::debug!("Received callback (raw {:?}) {:?}", raw_data, data);
+
+    match data {
+        CallbackData::Profile { associate } => {
+            bot.answer_callback_query(q.id).await?;
+            let chat_id = q
+                .message
+                .map(|message| message.chat().id)
+                .unwrap_or(q.from.id.into());
+            send_participant_profile(&bot, &pool, chat_id, associate as i64).await?;
+        }
+        CallbackData::Legend {
+            associate,
+            submission,
+        } => {
+            bot.answer_callback_query(q.id).await?;
+            let Some(message) = q.message else {
+                return Ok(());
+            };
+            let challenges = submission_remaining_challenges(&pool, submission).await?;
+            let back = CallbackData::Back {
+                associate,
+                submission,
+            };
+            bot.edit_message_text(
+                message.chat().id,
+                message.id(),
+                challenge_legend(&challenges),
+            )
+            .parse_mode(ParseMode::Html)
+            .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback("⬅️ Back", back.to_string()),
+            ]]))
+            .await?;
+        }
+        CallbackData::Back {
+            associate,
+            submission,
+        } => {
+            bot.answer_callback_query(q.id).await?;
+            let Some(message) = q.message else {
+                return Ok(());
+            };
+            let challenges = submission_remaining_challenges(&pool, submission).await?;
+            let caption: Option<String> =
+                sqlx::query_scalar("SELECT caption FROM submissions WHERE message_id = $1")
+                    .bind(submission)
+                    .fetch_optional(&pool)
+                    .await?
+                    .flatten();
+            let tagged = tagged_challenge(&caption.unwrap_or_default(), &challenges);
+            bot.edit_message_text(
+                message.chat().id,
+                message.id(),
+                "Select challenge or action",
+            )
+            .reply_markup(make_keyboard(
+                associate,
+                submission,
+                challenges,
+                tagged,
+                judge_keyboard_columns(&pool).await?,
+            ))
+            .await?;
+        }
+        CallbackData::Reopen {
+            associate,
+            submission,
+        } => {
+            bot.answer_callback_query(q.id).await?;
+            let challenges = submission_remaining_challenges(&pool, submission).await?;
+            let caption: Option<String> =
+                sqlx::query_scalar("SELECT caption FROM submissions WHERE message_id = $1")
+                    .bind(submission)
+                    .fetch_optional(&pool)
+                    .await?
+                    .flatten();
+            let tagged = tagged_challenge(&caption.unwrap_or_default(), &challenges);
+            let chat_id = q
+                .message
+                .as_ref()
+                .map(|message| message.chat().id)
+                .unwrap_or(cfg.judge_chat);
+            let mut request = bot
+                .send_message(chat_id, "Select challenge or action")
+                .reply_markup(make_keyboard(
+                    associate,
+                    submission,
+                    challenges,
+                    tagged,
+                    judge_keyboard_columns(&pool).await?,
+                ));
+            request.reply_parameters = q.message.map(|message| ReplyParameters::new(message.id()));
+            request.await?;
+        }
+        CallbackData::Judge { .. } | CallbackData::Tier { .. } => {
+            let (associate, image_ref, verdict, tier) = match data {
+                CallbackData::Judge {
+                    associate,
+                    submission,
+                    verdict,
+                } => (associate, submission, verdict, None),
+                CallbackData::Tier {
+                    associate,
+                    submission,
+                    challenge,
+                    tier,
+                } => (
+                    associate,
+                    submission,
+                    Verdict::Challenge(challenge),
+                    Some(tier),
+                ),
+                _ => unreachable!(),
+            };
+            if verdict == Verdict::Flag {
+                let flagged =
+                    sqlx::query("UPDATE submissions SET flagged = 1 WHERE message_id = $1")
+                        .bind(image_ref)
+                        .execute(&pool)
+                        .await?
+                        .rows_affected();
+                let text = if flagged == 0 {
+                    "This submission doesn't exist anymore"
+                } else {
+                    "Flagged for review, see /list_flagged_submissions"
+                };
+                bot.answer_callback_query(q.id).text(text).await?;
+                return Ok(());
+            }
+            let challenge = match verdict {
+                Verdict::Flag => unreachable!(),
+                Verdict::Unclear => special_challenge("___unclear"),
+                Verdict::Invalid => special_challenge("___invalid"),
+                Verdict::Challenge(id) => {
+                    sqlx::query_as::<_, Challenge>(
+                        "SELECT rowid AS id, * FROM challenges WHERE rowid = $1",
+                    )
+                    .bind(id)
+                    .fetch_optional(&pool)
+                    .await?
+                }
+            };
+            let Some(challenge) = challenge else {
+                bot.answer_callback_query(q.id)
+                    .text("This challenge doesn't exist anymore")
+                    .show_alert(true)
+                    .await?;
+                return Ok(());
+            };
+            // Challenges with tiers ask for the quality of the submission first
+            let tiers = challenge.tier_points();
+            if tier.is_none() && !tiers.is_empty() {
+                bot.answer_callback_query(q.id).await?;
+                if let Some(message) = q.message {
+                    bot.edit_message_text(
+                        message.chat().id,
+                        message.id(),
+                        format!("How good is it? ({})", challenge.name),
+                    )
+                    .reply_markup(tier_keyboard(associate, image_ref, &challenge))
+                    .await?;
+                }
+                return Ok(());
+            }
+            if tier.is_some_and(|tier| tier == 0 || tier > tiers.len()) {
+                bot.answer_callback_query(q.id)
+                    .text("This tier doesn't exist anymore")
+                    .show_alert(true)
+                    .await?;
+                return Ok(());
+            }
+            let choice = match tier {
+                Some(tier) => format!("{} {}", challenge.name, "⭐".repeat(tier)),
+                None => challenge.name.clone(),
+            };
+
+            let mut callback_query = bot.answer_callback_query(q.id);
+            callback_query.show_alert = Some(true);
+            callback_query.text = Some(format!("Choice = {}", choice).clone());
+            callback_query.await?;
+
+            let judged = judge(
+                associate.to_string(),
+                image_ref.to_string(),
+                challenge.name,
+                tier,
+                Some(q.from.id),
+                &actions,
+                &pool,
+                &cfg,
+            )
+            .await;
+
+            // Edit text of the message to which the buttons were attached
+            let text = match judged {
+                Ok(()) => format!(
+                    "Decision <b>{choice}</b>\n\nOverwrite with '/judge {image_ref} [challenge]'"
+                ),
+                Err(BotError::SubmissionNotFound(_)) => {
+                    "This submission doesn't exist anymore".to_owned()
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(message) = q.message {
+                bot.edit_message_text(message.chat().id, message.id(), text)
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+            } else if let Some(id) = q.inline_message_id {
+                bot.edit_message_text_inline(id, text)
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+            }
+
+            refresh_pinned_scoreboard(&bot, &pool, cfg.judge_chat).await;
+            log::info!("Judge chose: {}", choice);
+        }
+    }
+
+    Ok(())
+}
+
+/// Record the verdict for a submission and notify the participant. `tier` is the quality
+/// tier picked for challenges with tiers, `judge_id` the maintainer who judged it.
+#[allow(clippy::too_many_arguments)]
+async fn judge(
+    associate: String,
+    submission_ref: String,
+    challenge: String,
+    tier: Option<usize>,
+    judge_id: Option<UserId>,
+    actions: &impl BotActions,
+    pool: &SqlitePool,
+    cfg: &ConfigParameters,
+) -> BotResult {
+    let (date, excluded, media_type, test, team): (String, bool, i32, bool, String) =
+        sqlx::query_as(
+            "SELECT date, excluded, type, test, team FROM submissions WHERE message_id = $1",
+        )
+        .bind(submission_ref.clone())
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| BotError::SubmissionNotFound(submission_ref.clone()))?;
+    // Test submissions go through the whole flow but are stored as invalid, which keeps
+    // them out of every score
+    let (mut points, mut valid) =
+        judgement_points(pool, &challenge, tier, &date, excluded || test, media_type).await?;
+    let claimed_by = match valid {
+        true => claimed_by(pool, &challenge, &team).await?,
+        false => None,
+    };
+    if claimed_by.is_some() {
+        (points, valid) = (0, false);
+    }
+
+    let previous_challenge: Option<String> =
+        sqlx::query_scalar("SELECT challenge_name FROM judgement WHERE submission_id = $1")
+            .bind(submission_ref.clone())
+            .fetch_optional(pool)
+            .await?;
+
+    sqlx::query("UPDATE submissions SET flagged = 0 WHERE message_id = $1")
+        .bind(submission_ref.clone())
+        .execute(pool)
+        .await?;
+    sqlx::query(UPSERT_JUDGEMENT_QUERY)
+        .bind(submission_ref.clone())
+        .bind(challenge.clone())
+        .bind(points)
+        .bind(valid)
+        .bind(tier.map(|tier| tier as i64))
+        .bind(judge_id.map(|id| id.0 as i64))
+        .bind(
+            claimed_by
+                .as_ref()
+                .map(|team| format!("already claimed by {}", team)),
+        )
+        .execute(pool)
+        .await?;
+    // Judging it as another challenge can move other teams up on the old one's podium
+    if let Some(previous) = previous_challenge.as_ref().filter(|x| **x != challenge) {
+        award_podium_bonus(pool, previous).await?;
+    }
+    award_podium_bonus(pool, &challenge).await?;
+    let points: i32 = sqlx::query_scalar("SELECT points FROM judgement WHERE submission_id = $1")
+        .bind(submission_ref.clone())
+        .fetch_one(pool)
+        .await?;
+
+    // There is no participant message to react to
+    if test {
+        log::info!("Test submission {} judged as {}", submission_ref, challenge);
+        return Ok(());
+    }
+    // Judging it again as the same challenge would post it twice
+    if previous_challenge.as_ref() != Some(&challenge) {
+        post_to_challenge_topic(actions, pool, cfg, &associate, &submission_ref, &challenge)
+            .await?;
+    }
+    if let Some(claimed_by) = claimed_by {
+        let participant_chat = ChatId::from(UserId(associate.parse::<u64>().unwrap()));
+        let submission_id = MessageId(submission_ref.parse::<i32>().unwrap());
+        actions
+            .send_text(
+                participant_chat,
+                format!(
+                    "Too late, team {} completed '{}' first. Only the first team gets its points.",
+                    claimed_by, challenge
+                ),
+                SendOptions {
+                    reply_to: Some(submission_id),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        react_or_reply(actions, participant_chat, submission_id, None).await?;
+        return Ok(());
+    }
+    notify_participant(
+        actions,
+        pool,
+        &associate,
+        &submission_ref,
+        &challenge,
+        points,
+        valid,
+    )
+    .await?;
+    if valid {
+        announce_first_claim(actions, pool, &challenge, &team, 1).await?;
+    }
+    Ok(())
+}
+
+/// Team the submission of /self_test is made for, not a team participants can join
+const SELF_TEST_TEAM: &str = "__self_test__";
+
+/// Run a submission of a made-up team through storing, judging, notifying and scoring, with
+/// a dry run bot so nothing reaches Telegram. The submission is removed afterwards, even if a
+/// stage failed. Returns the outcome of each stage.
+async fn self_test(
+    pool: &SqlitePool,
+    cfg: &ConfigParameters,
+    maintainer: UserId,
+) -> Vec<(&'static str, Result<String, String>)> {
+    let mut stages = vec![];
+    let stored: Result<i32, sqlx::Error> = sqlx::query_scalar(
+        "INSERT INTO submissions (message_id, user, team, date, caption, type)
+        SELECT MIN(COALESCE(MIN(message_id), 0), 0) - 1, $1, $2, datetime('now'), $3, 0
+        FROM submissions
+        RETURNING message_id",
+    )
+    .bind(maintainer.0 as i64)
+    .bind(SELF_TEST_TEAM)
+    .bind("🧪 Self test")
+    .fetch_one(pool)
+    .await;
+    let message_id = match stored {
+        Ok(message_id) => {
+            stages.push(("Store submission", Ok(format!("id {}", message_id))));
+            message_id
+        }
+        Err(e) => {
+            stages.push(("Store submission", Err(e.to_string())));
+            return stages;
+        }
+    };
+
+    stages.extend(self_test_stages(pool, cfg, maintainer, message_id).await);
+
+    let cleanup = async {
+        let challenge: Option<String> =
+            sqlx::query_scalar("SELECT challenge_name FROM judgement WHERE submission_id = $1")
+                .bind(message_id)
+                .fetch_optional(pool)
+                .await?;
+        sqlx::query("DELETE FROM judgement WHERE submission_id = $1")
+            .bind(message_id)
+            .execute(pool)
+            .await?;
+        sqlx::query("DELETE FROM submissions WHERE message_id = $1")
+            .bind(message_id)
+            .execute(pool)
+            .await?;
+        // The made-up team may have taken a podium place of a real team
+        match challenge {
+            Some(challenge) => award_podium_bonus(pool, &challenge).await,
+            None => Ok(()),
+        }
+    };
+    stages.push((
+        "Clean up",
+        match cleanup.await {
+            Ok(_) => Ok("submission and judgement removed".to_owned()),
+            Err(e) => Err(format!("{}, remove submission {} by hand", e, message_id)),
+        },
+    ));
+    stages
+}
+
+/// Judging, notification and score stages of [`self_test`], stopping at the first failure
+async fn self_test_stages(
+    pool: &SqlitePool,
+    cfg: &ConfigParameters,
+    maintainer: UserId,
+    message_id: i32,
+) -> Vec<(&'static str, Result<String, String>)> {
+    let mut stages = vec![];
+    // Challenges only one team can win would be claimed by the made-up team
+    let challenge: Option<String> = match sqlx::query_scalar(
+        "SELECT name FROM challenges WHERE first_only = 0 ORDER BY rowid LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(challenge) => challenge,
+        Err(e) => {
+            stages.push(("Judge", Err(e.to_string())));
+            return stages;
+        }
+    };
+    let Some(challenge) = challenge else {
+        stages.push((
+            "Judge",
+            Err("no challenge to judge the submission as".to_owned()),
+        ));
+        return stages;
+    };
+
+    let dry_run = DryRunBot::default();
+    let judged = judge(
+        maintainer.to_string(),
+        message_id.to_string(),
+        challenge.clone(),
+        None,
+        Some(maintainer),
+        &dry_run,
+        pool,
+        cfg,
+    )
+    .await;
+    let judgement: Result<Option<(i32, bool)>, sqlx::Error> =
+        sqlx::query_as("SELECT points, valid FROM judgement WHERE submission_id = $1")
+            .bind(message_id)
+            .fetch_optional(pool)
+            .await;
+    let points = match (judged, judgement) {
+        (Err(e), _) => Err(e.to_string()),
+        (_, Err(e)) => Err(e.to_string()),
+        (Ok(()), Ok(None)) => Err("no judgement was stored".to_owned()),
+        (Ok(()), Ok(Some((_, false)))) => Err("the judgement was stored as invalid".to_owned()),
+        (Ok(()), Ok(Some((points, true)))) => Ok(points),
+    };
+    let points = match points {
+        Ok(points) => {
+            stages.push((
+                "Judge",
+                Ok(format!("valid as {:?} for {} point(s)", challenge, points)),
+            ));
+            points
+        }
+        Err(problem) => {
+            stages.push(("Judge", Err(problem)));
+            return stages;
+        }
+    };
+
+    let reacted = dry_run.recorded().iter().any(|action| {
+        matches!(
+            action,
+            RecordedAction::React { message_id: id, emoji: Some(_), .. } if id.0 == message_id
+        )
+    });
+    stages.push((
+        "Notify participant",
+        match reacted {
+            true => Ok(format!("{} call(s) recorded", dry_run.recorded().len())),
+            false => Err("the submission got no reaction".to_owned()),
+        },
+    ));
+
+    stages.push((
+        "Score",
+        match team_score(pool, SELF_TEST_TEAM).await {
+            Ok(score) if score == points as i64 => Ok(format!("{} point(s)", score)),
+            Ok(score) => Err(format!("expected {} point(s), got {}", points, score)),
+            Err(e) => Err(e.to_string()),
+        },
+    ));
+    stages
+}
+
+/// Copy a judged submission into the judge forum topic of `challenge`, if it has one. Only
+/// possible if the first judge chat is a forum, otherwise everything stays in the judge chats.
+async fn post_to_challenge_topic(
+    actions: &impl BotActions,
+    pool: &SqlitePool,
+    cfg: &ConfigParameters,
+    associate: &str,
+    submission_ref: &str,
+    challenge: &str,
+) -> BotResult {
+    if !cfg.judge_chat_is_forum {
+        return Ok(());
+    }
+    let thread_id: Option<i32> =
+        sqlx::query_scalar("SELECT forum_thread_id FROM challenges WHERE name = $1")
+            .bind(challenge)
+            .fetch_optional(pool)
+            .await?
+            .flatten();
+    let Some(thread_id) = thread_id else {
+        return Ok(());
+    };
+    let participant_chat = ChatId::from(UserId(associate.parse::<u64>().unwrap()));
+    let submission_id = MessageId(submission_ref.parse::<i32>().unwrap());
+    // The verdict is already stored, a missing topic must not undo it
+    if let Err(e) = actions
+        .forward(
+            cfg.judge_chat,
+            participant_chat,
+            submission_id,
+            Some(ThreadId(MessageId(thread_id))),
+        )
+        .await
+    {
+        log::warn!(
+            "Could not copy submission {} to the topic of {}: {}",
+            submission_ref,
+            challenge,
+            e
+        );
+    }
+    Ok(())
+}
+
+/// Recompute the podium of `challenge`: the first valid judgement of each team, ordered by
+/// submission time (then message id for simultaneous ones), gets the bonus of its place on
+/// top of its points. Recomputed as a whole since judging order can differ from submission
+/// order.
+async fn award_podium_bonus(pool: &SqlitePool, challenge: &str) -> Result<(), sqlx::Error> {
+    let details = sqlx::query_as::<_, Challenge>("SELECT * FROM challenges WHERE name = $1")
+        .bind(challenge)
+        .fetch_optional(pool)
+        .await?;
+    let bonuses = details
+        .map(|details| details.podium_bonuses())
+        .unwrap_or_default();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE judgement SET points = points - bonus, bonus = 0, podium_place = NULL
+        WHERE challenge_name = $1 AND (bonus != 0 OR podium_place IS NOT NULL)",
+    )
+    .bind(challenge)
+    .execute(&mut *tx)
+    .await?;
+    if !bonuses.is_empty() {
+        let podium: Vec<i64> = sqlx::query_scalar(
+            "SELECT submission_id FROM (
+                SELECT j.submission_id, s.date, s.message_id,
+                    ROW_NUMBER() OVER (PARTITION BY s.team ORDER BY s.date, s.message_id) AS n
+                FROM judgement j
+                JOIN submissions s ON j.submission_id = s.message_id
+                WHERE j.challenge_name = $1 AND j.valid = 1
+            )
+            WHERE n = 1
+            ORDER BY date, message_id
+            LIMIT $2",
+        )
+        .bind(challenge)
+        .bind(bonuses.len() as i64)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (place, (submission_id, bonus)) in podium.into_iter().zip(bonuses).enumerate() {
+            sqlx::query(
+                "UPDATE judgement SET points = points + $1, bonus = $1, podium_place = $2
+                WHERE submission_id = $3",
+            )
+            .bind(bonus)
+            .bind(place as i64 + 1)
+            .bind(submission_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    tx.commit().await
+}
+
+/// Recompute the podiums of all challenges with a podium bonus, e.g. after judgements were
+/// removed
+async fn award_all_podium_bonuses(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let challenges: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM challenges WHERE podium_bonus IS NOT NULL")
+            .fetch_all(pool)
+            .await?;
+    for challenge in challenges {
+        award_podium_bonus(pool, &challenge).await?;
+    }
+    Ok(())
+}
 
-        keyboard.push(row);
+/// Medal of the first three podium places, the place number after that
+fn podium_medal(place: i64) -> String {
+    match place {
+        1 => "🥇".to_owned(),
+        2 => "🥈".to_owned(),
+        3 => "🥉".to_owned(),
+        _ => format!("#{}", place),
     }
-    keyboard.push(vec![
-        InlineKeyboardButton::callback(
-            "⚠️ Unclear",
-            format!("{}###{}###___unclear", associate, reference),
-        ),
-        InlineKeyboardButton::callback(
-            "❌ Invalid",
-            format!("{}###{}###___invalid", associate, reference),
-        ),
-    ]);
+}
 
-    InlineKeyboardMarkup::new(keyboard)
+/// Team other than `team` that already completed `challenge`, if only the first team to
+/// complete it gets points
+async fn claimed_by(
+    pool: &SqlitePool,
+    challenge: &str,
+    team: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT s.team
+        FROM judgement j
+        JOIN submissions s ON j.submission_id = s.message_id
+        JOIN challenges c ON j.challenge_name = c.name
+        WHERE c.name = $1 AND c.first_only AND j.valid = 1 AND s.team != $2
+        ORDER BY s.date
+        LIMIT 1",
+    )
+    .bind(challenge)
+    .bind(team)
+    .fetch_optional(pool)
+    .await
 }
 
-async fn join_team(
-    bot: Bot,
-    msg: Message,
-    cmd: ParticipantCommand,
-    lock: Arc<Mutex<()>>,
-    pool: SqlitePool,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    match cmd {
-        ParticipantCommand::JoinTeam(team) => {
-            if team.trim().len() == 0 {
-                bot.send_message(
-                    msg.chat.id,
-                    "Please provide a team name. /join_team followed by the team name",
-                )
-                .await?;
-                return Ok(());
-            }
-            let data = User {
-                id: msg.from.as_ref().unwrap().id.0 as i64,
-                team: team.to_owned(),
-                username: msg.from.as_ref().unwrap().username.clone(),
-                first_name: msg.from.as_ref().unwrap().first_name.clone(),
-                last_name: msg.from.as_ref().unwrap().last_name.clone(),
-            };
-            let result = sqlx::query(
-                "INSERT INTO users (id, team, username, first_name, last_name, created_at)
-                VALUES ($1, $2, $3, $4, $5, datetime('now'))
-                ON CONFLICT(id) DO UPDATE SET team = excluded.team",
+/// Tell all members of `team` that they won `challenge` if it is a challenge only the first
+/// team gets points for and the `new` valid judgements just recorded are its only ones
+async fn announce_first_claim(
+    actions: &impl BotActions,
+    pool: &SqlitePool,
+    challenge: &str,
+    team: &str,
+    new: i64,
+) -> BotResult {
+    let first_claim: bool = sqlx::query_scalar(
+        "SELECT c.first_only AND COUNT(j.submission_id) = $2
+        FROM challenges c
+        LEFT JOIN judgement j ON j.challenge_name = c.name AND j.valid = 1
+        WHERE c.name = $1
+        GROUP BY c.name",
+    )
+    .bind(challenge)
+    .bind(new)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or_default();
+    if !first_claim {
+        return Ok(());
+    }
+    let members: Vec<i64> = sqlx::query_scalar("SELECT id FROM users WHERE team = $1")
+        .bind(team)
+        .fetch_all(pool)
+        .await?;
+    for member in members {
+        let sent = actions
+            .send_text(
+                ChatId(member),
+                format!(
+                    "🏆 Your team was the first to complete '{}' and secured its points!",
+                    challenge
+                ),
+                SendOptions::default(),
             )
-            .bind(data.id)
-            .bind(data.team)
-            .bind(data.username)
-            .bind(data.first_name)
-            .bind(data.last_name)
-            .execute(&pool)
             .await;
-            result.unwrap();
-            bot.send_message(msg.chat.id, format!("You joined team `{}`\n\nCheck the team members with /team\\_overview\\.\nDon't change your team \\(name\\) after the first submisssion; previous submissions will not count anymore", team))
-                .parse_mode(ParseMode::MarkdownV2)
-                .await?;
-
-            let _guard = lock.lock().await;
-            update_teams_in_forum(&bot, &pool).await?;
-        }
-        _ => {
-            unreachable!()
+        match sent {
+            Ok(_) => {}
+            Err(e) if e.is_unreachable_user() => {
+                log::warn!("Could not announce {} to {}: {}", challenge, member, e)
+            }
+            Err(e) => return Err(e),
         }
-    };
+    }
     Ok(())
 }
 
-async fn participant_commands_handler(
-    cfg: ConfigParameters,
-    bot: Bot,
-    me: teloxide::types::Me,
-    msg: Message,
-    cmd: ParticipantCommand,
-    pool: SqlitePool,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    if (msg.chat.is_group() || msg.chat.is_supergroup()) && !matches!(cmd, ParticipantCommand::Help)
-    {
-        bot.send_message(msg.chat.id, "Please use me in a private chat")
-            .await?;
-        return Ok(());
+/// Submission forwarded as message `$1` to judge chat `$2`; `$3` is the first judge chat,
+/// which submissions forwarded before the chat was stored went to
+const FORWARDED_SUBMISSION_QUERY: &str = "SELECT {columns} FROM submissions
+    WHERE forwarded_message_id = $1 AND COALESCE(judge_chat_id, $3) = $2";
+
+const UPSERT_JUDGEMENT_QUERY: &str = "INSERT INTO judgement (submission_id, challenge_name, points, valid, tier, judge_user_id, note) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT(submission_id) DO UPDATE SET challenge_name = excluded.challenge_name, points = excluded.points, valid = excluded.valid, tier = excluded.tier, judge_user_id = excluded.judge_user_id, note = excluded.note, podium_place = NULL, bonus = 0";
+
+/// Points and validity of a submission from `date` judged as `challenge`. A `tier` replaces
+/// the challenge's base points with the points of that tier.
+async fn judgement_points(
+    pool: &SqlitePool,
+    challenge: &str,
+    tier: Option<usize>,
+    date: &str,
+    excluded: bool,
+    media_type: i32,
+) -> BotResult<(i32, bool)> {
+    if special_challenge(challenge).is_some() || excluded {
+        return Ok((0, false));
     }
-    match cmd {
-        ParticipantCommand::Start => {
-            bot.send_message(
-                msg.chat.id,
-                format!("Hello {}", msg.chat.first_name().unwrap_or("Spree Breaker")),
+    let mut details = sqlx::query_as::<_, Challenge>("SELECT * FROM challenges WHERE name = $1")
+        .bind(challenge)
+        .fetch_one(pool)
+        .await?;
+    if let Some(tier) = tier {
+        details.points = details
+            .tier_points()
+            .get(tier.saturating_sub(1))
+            .copied()
+            .or(details.points);
+    }
+    let mut points = scoring::points_for(&details, date);
+    // Opt-in weighting of videos, which take more effort than photos
+    if media_type == 1 {
+        if let Some(multiplier) = config_value(pool, "video_points_multiplier").await? {
+            points = scoring::apply_multiplier(points, &multiplier);
+        }
+    }
+    Ok((points, true))
+}
+
+/// Message sent for a valid submission, `{challenge}` and `{points}` are replaced
+const DEFAULT_CHALLENGE_COMPLETED_MESSAGE: &str = "🎉 You completed '{challenge}' for {points}!";
+
+/// React to a participant's submission. Where reactions aren't possible, a reply takes the
+/// place of the reaction; clearing one is skipped since the verdict is sent as text anyway.
+async fn react_or_reply(
+    actions: &impl BotActions,
+    chat_id: ChatId,
+    message_id: MessageId,
+    emoji: Option<String>,
+) -> BotResult {
+    let accepted = emoji.is_some();
+    match actions.react(chat_id, message_id, emoji).await {
+        Err(e) if e.is_reaction_unsupported() => {
+            log::info!(
+                "Cannot react to {} in {}, falling back to a reply: {}",
+                message_id,
+                chat_id,
+                e
+            );
+            if accepted {
+                actions
+                    .send_text(
+                        chat_id,
+                        "✅ accepted".to_owned(),
+                        SendOptions {
+                            reply_to: Some(message_id),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+            }
+            Ok(())
+        }
+        reacted => reacted,
+    }
+}
+
+/// React to the judged submission and congratulate the participant, or explain why it
+/// doesn't count
+async fn notify_participant(
+    actions: &impl BotActions,
+    pool: &SqlitePool,
+    associate: &str,
+    submission_ref: &str,
+    challenge: &str,
+    points: i32,
+    valid: bool,
+) -> BotResult {
+    // All of this can fail since the user might have deleted their message
+    // TODO: Handle deleted messages better, don't just ignore
+    let participant_chat = ChatId::from(UserId(associate.parse::<u64>().unwrap()));
+    let submission_id = MessageId(submission_ref.parse::<i32>().unwrap());
+    if !valid {
+        let sent = actions
+            .send_text(
+                participant_chat,
+                match challenge {
+                    "___unclear" => {
+                        "Please reply to this message with a clear caption for your submission"
+                    }
+                    "___invalid" => "Your submission is invalid",
+                    _ => {
+                        "This submission was made for your previous team and doesn't count anymore"
+                    }
+                }
+                .to_string(),
+                SendOptions {
+                    reply_to: Some(submission_id),
+                    ..Default::default()
+                },
             )
             .await?;
-            bot.send_message(
-                msg.chat.id,
-                "Check /help for ways that I can provide you help. To get started with the photo challenge use /join_team followed by the team name. The team name must be identical for all team members."
+        // The participant can reply with a new caption instead of resending
+        if challenge == "___unclear" {
+            sqlx::query(
+                "UPDATE submissions SET clarification_message_id = $1 WHERE message_id = $2",
             )
+            .bind(sent.0)
+            .bind(submission_id.0)
+            .execute(pool)
             .await?;
         }
-        ParticipantCommand::Help => {
-            let text = if cfg.maintainers.contains(&msg.from.unwrap().id) {
-                format!(
-                    "{}\n\n{}",
-                    ParticipantCommand::descriptions(),
-                    MaintainerCommands::descriptions()
+        // Clear existing reactions
+        react_or_reply(actions, participant_chat, submission_id, None).await?;
+    } else {
+        react_or_reply(
+            actions,
+            participant_chat,
+            submission_id,
+            Some("❤".to_owned()),
+        )
+        .await?;
+        // Verdicts that need the participant to act are sent regardless
+        let muted: bool =
+            sqlx::query_scalar("SELECT muted_results FROM user_preferences WHERE user_id = $1")
+                .bind(participant_chat.0)
+                .fetch_optional(pool)
+                .await?
+                .unwrap_or_default();
+        if muted {
+            return Ok(());
+        }
+        let text = config_message(
+            pool,
+            "challenge_completed_message",
+            DEFAULT_CHALLENGE_COMPLETED_MESSAGE,
+        )
+        .await
+        .replace("{challenge}", challenge)
+        .replace(
+            "{points}",
+            &format_points_change(points as i64, &points_format(pool).await),
+        );
+        let sent = actions
+            .send_text(
+                participant_chat,
+                text,
+                SendOptions {
+                    reply_to: Some(submission_id),
+                    ..Default::default()
+                },
+            )
+            .await;
+        // The verdict is saved either way, a participant who blocked the bot just misses it
+        match sent {
+            Ok(_) => {}
+            Err(e) if e.is_unreachable_user() => {
+                log::warn!(
+                    "Could not congratulate {} for {}: {}",
+                    associate,
+                    submission_ref,
+                    e
                 )
-            } else if msg.chat.is_group() || msg.chat.is_supergroup() {
-                ParticipantCommand::descriptions()
-                    .username_from_me(&me)
-                    .to_string()
-            } else {
-                ParticipantCommand::descriptions().to_string()
-            };
-            bot.send_message(msg.chat.id, text).await?;
+            }
+            Err(e) => return Err(e),
         }
-        ParticipantCommand::JoinTeam(_team) => {
-            unreachable!("This should be handled by the join_team function");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actions::FailingCall;
+    use chrono::Timelike;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    const PARTICIPANT: i64 = 42;
+    const JUDGE_CHAT: ChatId = ChatId(-100);
+
+    /// Fresh database with all migrations, kept alive by its single connection
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    fn test_config(submissions_dir: &Path) -> ConfigParameters {
+        ConfigParameters {
+            maintainers: HashSet::new(),
+            judge_chat: JUDGE_CHAT,
+            judge_chats: vec![JUDGE_CHAT],
+            forum_chat: Recipient::Id(ChatId(-200)),
+            judge_chat_is_forum: false,
+            submissions_dir: submissions_dir.to_owned(),
         }
-        ParticipantCommand::TeamOverview => {
-            let team_members = sqlx::query_as::<_, User>(
-                "SELECT * FROM users WHERE team = (SELECT team FROM users WHERE id = $1)",
-            )
-            .bind(msg.from.as_ref().unwrap().id.0 as i64)
-            .fetch_all(&pool)
-            .await?;
+    }
 
-            let team = sqlx::query_as::<_, Team>(
-                "SELECT team, COUNT(*) AS count FROM users WHERE id = $1 LIMIT 1",
-            )
-            .bind(msg.from.as_ref().unwrap().id.0 as i64)
-            .fetch_one(&pool)
-            .await?;
+    /// `PARTICIPANT` as member of `team`
+    async fn join(pool: &SqlitePool, team: &str) {
+        sqlx::query("INSERT INTO users (id, first_name, team) VALUES ($1, 'Ada', $2)")
+            .bind(PARTICIPANT)
+            .bind(team)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
 
-            if team.count > 0 {
-                let team_members_text = if team_members.is_empty() {
-                    "No team members yet".to_owned()
-                } else {
-                    team_members
-                        .iter()
-                        .map(|x| format!("- {}", x.to_string()))
-                        .collect::<Vec<String>>()
-                        .join("\n")
-                };
-                log::warn!("{:?}", team);
-                bot.send_message(
-                    msg.chat.id,
-                    format!(
-                        "Overview team <code>{}</code>\n\n{} Member(s):\n{team_members_text}",
-                        team.team,
-                        team_members.len()
-                    ),
-                )
-                .parse_mode(ParseMode::Html)
-                .await?;
-            } else {
-                bot.send_message(msg.chat.id, "You are not yet part of a team")
-                    .await?;
+    /// Message of `PARTICIPANT` in its private chat, `fields` are added to the JSON
+    fn private_message(message_id: i32, fields: serde_json::Value) -> Message {
+        let mut message = serde_json::json!({
+            "message_id": message_id,
+            "date": 1732000000,
+            "chat": {"id": PARTICIPANT, "type": "private", "first_name": "Ada"},
+            "from": {"id": PARTICIPANT, "is_bot": false, "first_name": "Ada"},
+        });
+        message
+            .as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(message).unwrap()
+    }
+
+    fn photo_message(message_id: i32) -> (Media, Message) {
+        let msg = private_message(
+            message_id,
+            serde_json::json!({
+                "photo": [{
+                    "file_id": "photo-file",
+                    "file_unique_id": "photo-unique",
+                    "width": 640,
+                    "height": 480,
+                    "file_size": 1000,
+                }],
+            }),
+        );
+        let media = match msg.kind {
+            MessageKind::Common(MessageCommon {
+                media_kind: MediaKind::Photo(ref photo),
+                ..
+            }) => Media::Photo(photo.clone()),
+            _ => unreachable!(),
+        };
+        (media, msg)
+    }
+
+    /// Receive `msg` as submission, the judge queue is returned so it stays open
+    async fn submit(
+        actions: &DryRunBot,
+        pool: &SqlitePool,
+        cfg: &ConfigParameters,
+        (media, msg): (Media, Message),
+    ) -> (
+        BotResult,
+        tokio::sync::mpsc::UnboundedReceiver<QueuedSubmission>,
+    ) {
+        let (queue, queued) = tokio::sync::mpsc::unbounded_channel();
+        let result = receive_submission(
+            media,
+            msg,
+            Actions::DryRun(actions.clone()),
+            cfg.clone(),
+            pool.clone(),
+            Arc::new(AtomicBool::new(true)),
+            JudgeQueue(queue),
+            DownloadPermits(Arc::new(tokio::sync::Semaphore::new(1))),
+        )
+        .await;
+        (result, queued)
+    }
+
+    /// Bot whose requests fail right away instead of reaching Telegram
+    fn offline_bot() -> Bot {
+        Bot::new("0:offline").set_api_url(Url::parse("http://127.0.0.1:9").unwrap())
+    }
+
+    fn bot_info() -> teloxide::types::Me {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "is_bot": true,
+            "first_name": "Spree Break",
+            "username": "spreebreak_bot",
+            "can_join_groups": true,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": false,
+            "can_connect_to_business": false,
+            "has_main_web_app": false,
+        }))
+        .unwrap()
+    }
+
+    /// Message in the judge chat; anonymous admins send without a user, as the chat
+    fn judge_chat_message(text: &str, from: Option<i64>) -> Message {
+        let chat = serde_json::json!({"id": JUDGE_CHAT.0, "type": "supergroup", "title": "Judges"});
+        let mut message = serde_json::json!({
+            "message_id": 7,
+            "date": 1732000000,
+            "chat": chat,
+            "text": text,
+        });
+        match from {
+            Some(id) => {
+                message["from"] =
+                    serde_json::json!({"id": id, "is_bot": false, "first_name": "Judge"})
             }
+            None => message["sender_chat"] = chat,
         }
-        ParticipantCommand::Score => {
-            let user_id = msg.from.as_ref().unwrap().id.0 as i64;
-            #[derive(sqlx::FromRow, Debug)]
-            struct ChallengeExtended {
-                challenge_name: String,
-                points: i32,
-            }
-            // Join over the tables users, submissions and judgement for the user_id
-            let res = sqlx::query_as::<_, ChallengeExtended>(
-                "SELECT j.challenge_name, j.points
-                FROM judgement j
-                LEFT JOIN submissions s ON j.submission_id = s.message_id
-                LEFT JOIN users u ON s.team = u.team
-                WHERE u.id = $1 AND j.valid = 1",
-            )
-            .bind(user_id)
-            .fetch_all(&pool)
-            .await?;
-            let scores = res
-                .into_iter()
-                .map(|x| format!("- {} +{} pts.", x.challenge_name, x.points))
-                .collect::<Vec<String>>()
-                .join("\n");
+        serde_json::from_value(message).unwrap()
+    }
 
-            #[derive(sqlx::FromRow, Debug)]
-            struct Score {
-                score: i32,
+    #[tokio::test]
+    async fn team_commands_in_judge_chat_need_a_team() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let actions = DryRunBot::default();
+
+        participant_commands_handler(
+            test_config(dir.path()),
+            offline_bot(),
+            Actions::DryRun(actions.clone()),
+            bot_info(),
+            judge_chat_message("/score", Some(99)),
+            ParticipantCommand::Score,
+            pool,
+        )
+        .await
+        .unwrap();
+
+        match &actions.recorded()[..] {
+            [RecordedAction::SendText { chat_id, text }] => {
+                assert_eq!(*chat_id, JUDGE_CHAT);
+                assert!(text.contains("isn't available in the judge chat"));
             }
-            let res = sqlx::query_as::<_, Score>(
-                "SELECT SUM(j.points) as score
-                    FROM judgement j
-                    LEFT JOIN submissions s ON j.submission_id = s.message_id
-                    LEFT JOIN users u ON s.team = u.team
-                    WHERE u.id = $1 AND j.valid = 1",
-            )
-            .bind(user_id)
+            recorded => panic!("unexpected calls {:?}", recorded),
+        }
+    }
+
+    #[tokio::test]
+    async fn teams_cant_be_joined_in_judge_chat() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let actions = DryRunBot::default();
+
+        join_team(
+            offline_bot(),
+            Actions::DryRun(actions.clone()),
+            judge_chat_message("/join_team Rocket", Some(99)),
+            ParticipantCommand::JoinTeam("Rocket".to_owned()),
+            test_config(dir.path()),
+            Arc::new(Mutex::new(())),
+            pool.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            actions.recorded(),
+            [RecordedAction::SendText {
+                chat_id: JUDGE_CHAT,
+                text: "Teams can't be joined from the judge chat. Send /join_team to me in a private chat.".to_owned(),
+            }]
+        );
+        let members: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
             .fetch_one(&pool)
-            .await?;
-            // Get the number of submissions of the team of the current user and how many of them appear in the table judgement
-            let res_submissions = sqlx::query_as::<_, Score>(
-                "SELECT COUNT(*) as score
-                    FROM submissions s
-                    LEFT JOIN users u ON s.team = u.team
-                    WHERE u.id = $1",
-            )
-            .bind(user_id)
+            .await
+            .unwrap();
+        assert_eq!(members, 0);
+    }
+
+    #[tokio::test]
+    async fn submission_without_sender_is_an_error() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let cfg = test_config(dir.path());
+        let actions = DryRunBot::default();
+        let (media, mut msg) = photo_message(7);
+        msg.from = None;
+
+        let (result, _queued) = submit(&actions, &pool, &cfg, (media, msg)).await;
+
+        assert!(matches!(result, Err(BotError::NoSender)));
+        assert_eq!(actions.recorded(), []);
+    }
+
+    #[tokio::test]
+    async fn joining_without_sender_is_an_error() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let mut msg = private_message(7, serde_json::json!({"text": "/join_team Rocket"}));
+        msg.from = None;
+
+        let result = join_team(
+            offline_bot(),
+            Actions::DryRun(DryRunBot::default()),
+            msg,
+            ParticipantCommand::JoinTeam("Rocket".to_owned()),
+            test_config(dir.path()),
+            Arc::new(Mutex::new(())),
+            pool.clone(),
+        )
+        .await;
+
+        assert!(matches!(result, Err(BotError::NoSender)));
+        let members: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(members, 0);
+    }
+
+    #[tokio::test]
+    async fn team_command_without_sender_is_an_error() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let msg = judge_chat_message("/score", None);
+
+        let result = participant_commands_handler(
+            test_config(dir.path()),
+            offline_bot(),
+            Actions::DryRun(DryRunBot::default()),
+            bot_info(),
+            msg,
+            ParticipantCommand::Score,
+            pool,
+        )
+        .await;
+
+        assert!(matches!(result, Err(BotError::NoSender)));
+    }
+
+    #[tokio::test]
+    async fn accepted_reply_replaces_unsupported_reaction() {
+        let pool = test_pool().await;
+        let actions = DryRunBot::default();
+        actions.fail(FailingCall::React);
+
+        notify_participant(&actions, &pool, "42", "7", "landmark", 3, true)
+            .await
+            .unwrap();
+
+        let recorded = actions.recorded();
+        assert_eq!(
+            recorded[..2],
+            [
+                RecordedAction::React {
+                    chat_id: ChatId(PARTICIPANT),
+                    message_id: MessageId(7),
+                    emoji: Some("❤".to_owned()),
+                },
+                RecordedAction::SendText {
+                    chat_id: ChatId(PARTICIPANT),
+                    text: "✅ accepted".to_owned(),
+                },
+            ]
+        );
+        // Clearing a reaction that isn't possible needs no reply
+        notify_participant(&actions, &pool, "42", "7", "___invalid", 0, false)
+            .await
+            .unwrap();
+        assert!(!actions.recorded()[recorded.len()..].contains(&recorded[1]));
+    }
+
+    #[tokio::test]
+    async fn submission_is_stored_with_the_senders_team() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let cfg = test_config(dir.path());
+        join(&pool, "Rocket").await;
+        let actions = DryRunBot::default();
+
+        let (result, _queued) = submit(&actions, &pool, &cfg, photo_message(7)).await;
+
+        result.unwrap();
+        let (team, user): (String, i64) =
+            sqlx::query_as("SELECT team, user FROM submissions WHERE message_id = 7")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!((team.as_str(), user), ("Rocket", PARTICIPANT));
+    }
+
+    #[tokio::test]
+    async fn judges_see_the_stored_submission_time() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let cfg = test_config(dir.path());
+        join(&pool, "Rocket").await;
+        let actions = DryRunBot::default();
+        let before = chrono::Utc::now().naive_utc().with_nanosecond(0).unwrap();
+
+        let (result, mut queued) = submit(&actions, &pool, &cfg, photo_message(7)).await;
+        result.unwrap();
+        let submission = queued.try_recv().unwrap();
+        deliver_to_judges(&Actions::DryRun(actions.clone()), &cfg, &pool, submission)
+            .await
+            .unwrap();
+
+        let date: String = sqlx::query_scalar("SELECT date FROM submissions WHERE message_id = 7")
             .fetch_one(&pool)
-            .await?;
-            bot.send_message(
-                msg.chat.id,
-                format!(
-                    "{scores}\n\nTotal score from {} submissions: {}",
-                    res_submissions.score, res.score
-                ),
-            )
-            .await?;
-        }
-        ParticipantCommand::Schedule => {
-            let source = sqlx::query_as::<_, Config>(
-                "SELECT name, value FROM config WHERE name = 'schedule_source'",
-            )
-            .fetch_optional(&pool)
-            .await?
-            .unwrap_or(Config {
-                name: "schedule_source".to_owned(),
-                value: "file::assets/schedule.png".to_owned(),
-            });
-            log::trace!("Load schedule config = {:?}", source);
-            let parts: Vec<&str> = source.value.split("::").collect();
-            let (mode, path) = (parts[0], parts[1]);
-            let file = match mode {
-                "file" => InputFile::file(Path::new(path)),
-                "url" => InputFile::url(Url::parse(path)?),
-                _ => unimplemented!("Unknown mode"),
-            };
-            bot.send_photo(msg.chat.id, file).await?;
+            .await
+            .unwrap();
+        let stored = scoring::parse_date(&date).unwrap();
+        assert!(before <= stored && stored <= chrono::Utc::now().naive_utc());
+        let shown = actions.recorded().into_iter().any(|action| {
+            matches!(action, RecordedAction::SendText { chat_id, text }
+                if chat_id == JUDGE_CHAT && text.contains(&format!("Time: {}\n", date)))
+        });
+        assert!(shown);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_forum_updates_create_each_topic_once() {
+        let pool = test_pool().await;
+        for (id, team) in [(1, "Rocket"), (2, "Rocket"), (3, "Magma"), (4, "Aqua")] {
+            sqlx::query("INSERT INTO users (id, first_name, team) VALUES ($1, 'Ada', $2)")
+                .bind(id)
+                .bind(team)
+                .execute(&pool)
+                .await
+                .unwrap();
         }
-        ParticipantCommand::SurvivalGuide => {
-            let source = sqlx::query_as::<_, Config>(
-                "SELECT name, value FROM config WHERE name = 'city_guide'",
+        let actions = DryRunBot::default();
+        let lock = Arc::new(Mutex::new(()));
+        let forum_chat = Recipient::Id(ChatId(-200));
+
+        let updates = (0..16).map(|_| {
+            let (actions, pool, lock, forum_chat) = (
+                actions.clone(),
+                pool.clone(),
+                lock.clone(),
+                forum_chat.clone(),
+            );
+            tokio::spawn(
+                async move { update_teams_in_forum(&actions, &pool, &lock, &forum_chat).await },
             )
-            .fetch_optional(&pool)
-            .await?
-            .unwrap_or(Config {
-                name: "schedule_source".to_owned(),
-                value: "file::assets/survival_guide.pdf".to_owned(),
-            });
-            log::trace!("Load schedule config = {:?}", source);
-            let parts: Vec<&str> = source.value.split("::").collect();
-            let (mode, path) = (parts[0], parts[1]);
-            let file = match mode {
-                "file" => InputFile::file(Path::new(path)),
-                "url" => InputFile::url(Url::parse(path)?),
-                _ => unimplemented!("Unknown mode"),
-            };
-            bot.send_document(msg.chat.id, file).await?;
+        });
+        for result in futures::future::join_all(updates).await {
+            result.unwrap().unwrap();
         }
-        ParticipantCommand::EmergencyInformation => {
-            #[derive(sqlx::FromRow, Debug)]
-            struct SafetyTeam {
-                name: String,
-                phone: String,
-            }
-            // If the hour is before 6am substract 24 from Utc::now then format the date
-            let now = chrono::Utc::now();
-            let now = if now.hour() < 6 {
-                log::trace!("Safety team: before 6am, subtract 1 day");
-                now - chrono::Duration::hours(24)
-            } else {
-                now
-            };
-            let current_date = now.format("%Y-%m-%d").to_string();
-            log::trace!("Current date = {:?}", current_date);
 
-            let team = sqlx::query_as::<_, SafetyTeam>(
-                "SELECT name, phone FROM safety_team WHERE date = $1",
+        let mut created = actions
+            .recorded()
+            .into_iter()
+            .filter_map(|action| match action {
+                RecordedAction::CreateTopic { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        created.sort();
+        assert_eq!(created, ["Aqua (0)", "Magma (0)", "Rocket (0)"]);
+        let forums: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM forums")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(forums, 3);
+    }
+
+    #[tokio::test]
+    async fn excluded_submission_scores_nothing() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        join(&pool, "Magma").await;
+        sqlx::query(
+            "INSERT INTO challenges (name, short_name, points) VALUES ('landmark', 'lm', 3)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // Submission 7 was made for the participant's previous team
+        sqlx::query(
+            "INSERT INTO submissions (message_id, user, team, date, caption, type, excluded)
+            VALUES (7, $1, 'Rocket', '2024-11-20 10:00:00', '', 0, 1),
+            (8, $1, 'Magma', '2024-11-20 11:00:00', '', 0, 0)",
+        )
+        .bind(PARTICIPANT)
+        .execute(&pool)
+        .await
+        .unwrap();
+        let actions = DryRunBot::default();
+        let cfg = test_config(dir.path());
+
+        for submission in ["7", "8"] {
+            judge(
+                PARTICIPANT.to_string(),
+                submission.to_owned(),
+                "landmark".to_owned(),
+                None,
+                None,
+                &actions,
+                &pool,
+                &cfg,
             )
-            .bind(current_date)
-            .fetch_all(&pool)
-            .await?;
-            log::trace!("Safety team = {:?}", team);
-            let team_list = if team.is_empty() {
-                "No safety team available right now".to_owned()
-            } else {
-                team.iter()
-                    .map(|x| format!("{}: {}", x.name, x.phone))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            };
-            bot.send_message(msg.chat.id, format!("Our safety team right now. Do not hesitate to talk to any other tutors.\n{team_list}\n\n🚑 <b>Fire brigade & ambulance: +112</b>\n👮 Police: +110")).parse_mode(ParseMode::Html).await?;
+            .await
+            .unwrap();
         }
-    };
-    Ok(())
-}
 
-async fn callback_handler(
-    bot: Bot,
-    pool: SqlitePool,
-    q: CallbackQuery,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if let Some(raw_choice) = q.data {
-        let parts = raw_choice.split("###").collect::<Vec<&str>>();
-        assert_eq!(parts.len(), 3);
-        let (associate, image_ref, choice) = (parts[0], parts[1], parts[2]);
-        log::debug!(
-            "Received callback (raw {:?}) assoc={:?} ref={:?} choice={:?}",
-            associate,
-            raw_choice,
-            image_ref,
-            choice
-        );
+        let judged: Vec<(i64, i32, bool)> = sqlx::query_as(
+            "SELECT submission_id, points, valid FROM judgement ORDER BY submission_id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(judged, [(7, 0, false), (8, 3, true)]);
+        assert!(actions.recorded().contains(&RecordedAction::SendText {
+            chat_id: ChatId(PARTICIPANT),
+            text: "This submission was made for your previous team and doesn't count anymore"
+                .to_owned(),
+        }));
+    }
 
-        let mut callback_query = bot.answer_callback_query(q.id);
-        callback_query.show_alert = Some(true);
-        callback_query.text = Some(format!("Choice = {}", choice).clone());
-        callback_query.await?;
+    #[tokio::test]
+    async fn switching_teams_hands_on_the_podium() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        join(&pool, "Rocket").await;
+        sqlx::query(
+            "INSERT INTO challenges (name, short_name, points, podium_bonus)
+            VALUES ('landmark', 'lm', 3, '5,2');
+            INSERT INTO submissions (message_id, user, team, date, caption, type)
+            VALUES (7, $1, 'Rocket', '2024-11-20 10:00:00', '', 0),
+            (8, 99, 'Magma', '2024-11-20 11:00:00', '', 0);
+            INSERT INTO judgement (submission_id, challenge_name, points, valid)
+            VALUES (7, 'landmark', 3, 1), (8, 'landmark', 3, 1)",
+        )
+        .bind(PARTICIPANT)
+        .execute(&pool)
+        .await
+        .unwrap();
+        award_podium_bonus(&pool, "landmark").await.unwrap();
 
-        judge(
-            associate.to_owned(),
-            image_ref.to_owned(),
-            choice.to_owned(),
-            &bot,
-            &pool,
+        // The confirmation fails on the offline bot, after the switch is stored
+        let _ = join_team(
+            offline_bot(),
+            Actions::DryRun(DryRunBot::default()),
+            private_message(20, serde_json::json!({"text": "/join_team Magma"})),
+            ParticipantCommand::JoinTeam("Magma".to_owned()),
+            test_config(dir.path()),
+            Arc::new(Mutex::new(())),
+            pool.clone(),
         )
-        .await?;
+        .await;
 
-        // Edit text of the message to which the buttons were attached
-        let text =
-            format!("Decision <b>{choice}</b>\n\nOverwrite with '/judge {image_ref} [challenge]'");
-        if let Some(message) = q.message {
-            bot.edit_message_text(message.chat().id, message.id(), text)
-                .parse_mode(ParseMode::Html)
-                .await?;
-        } else if let Some(id) = q.inline_message_id {
-            bot.edit_message_text_inline(id, text)
-                .parse_mode(ParseMode::Html)
-                .await?;
+        let judged: Vec<(i64, i32, i32, Option<i64>)> = sqlx::query_as(
+            "SELECT submission_id, points, bonus, podium_place FROM judgement ORDER BY submission_id",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(judged, [(7, 0, 0, None), (8, 8, 5, Some(1))]);
+    }
+
+    #[tokio::test]
+    async fn pending_warning_survives_bursts() {
+        let pool = test_pool().await;
+        set_config_value(&pool, "pending_submissions_warning_threshold", "2")
+            .await
+            .unwrap();
+        let actions = DryRunBot::default();
+        let warning = |pending: i64| RecordedAction::SendText {
+            chat_id: JUDGE_CHAT,
+            text: format!("⚠️ {} submissions pending, please help judge", pending),
+        };
+
+        // Three submissions arrive before the first check, skipping past the threshold
+        sqlx::query(
+            "INSERT INTO submissions (message_id, user, team, date, caption, type)
+            VALUES (7, $1, 'Rocket', '2024-11-20 10:00:00', '', 0),
+            (8, $1, 'Rocket', '2024-11-20 10:00:01', '', 0),
+            (9, $1, 'Rocket', '2024-11-20 10:00:02', '', 0)",
+        )
+        .bind(PARTICIPANT)
+        .execute(&pool)
+        .await
+        .unwrap();
+        for _ in 0..2 {
+            warn_about_pending_submissions(&actions, &pool, JUDGE_CHAT)
+                .await
+                .unwrap();
         }
+        assert_eq!(actions.recorded(), [warning(3)]);
 
-        log::info!("Judge chose: {}", choice);
+        // Once judged below the threshold, the next crossing warns again
+        sqlx::query(
+            "INSERT INTO judgement (submission_id, challenge_name, points, valid)
+            VALUES (7, 'landmark', 3, 1), (8, 'landmark', 3, 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        warn_about_pending_submissions(&actions, &pool, JUDGE_CHAT)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO submissions (message_id, user, team, date, caption, type)
+            VALUES (10, $1, 'Rocket', '2024-11-20 10:00:03', '', 0),
+            (11, $1, 'Rocket', '2024-11-20 10:00:04', '', 0)",
+        )
+        .bind(PARTICIPANT)
+        .execute(&pool)
+        .await
+        .unwrap();
+        warn_about_pending_submissions(&actions, &pool, JUDGE_CHAT)
+            .await
+            .unwrap();
+        assert_eq!(actions.recorded(), [warning(3), warning(3)]);
     }
 
-    Ok(())
-}
+    /// Pool with a tiered challenge and submission 7 forwarded to the judge chat as message 100
+    async fn forwarded_tiered_submission() -> SqlitePool {
+        let pool = test_pool().await;
+        join(&pool, "Rocket").await;
+        sqlx::query(
+            "INSERT INTO challenges (name, short_name, points, tiers)
+            VALUES ('landmark', 'lm', 3, '1,3,5');
+            INSERT INTO submissions (message_id, user, team, date, caption, type, forwarded_message_id)
+            VALUES (7, $1, 'Rocket', '2024-11-20 10:00:00', '#lm', 0, 100)",
+        )
+        .bind(PARTICIPANT)
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
 
-async fn judge(
-    associate: String,
-    submission_ref: String,
-    challenge: String,
-    bot: &Bot,
-    pool: &SqlitePool,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let mut points = 1;
-    let mut valid = true;
-    if challenge == "___unclear" || challenge == "___invalid" {
-        points = 0;
-        valid = false;
+    async fn judgements(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM judgement")
+            .fetch_one(pool)
+            .await
+            .unwrap()
     }
 
-    sqlx::query("INSERT INTO judgement (submission_id, challenge_name, points, valid) VALUES ($1, $2, $3, $4) ON CONFLICT(submission_id) DO UPDATE SET challenge_name = excluded.challenge_name")
-            .bind(submission_ref.clone())
-            .bind(challenge.clone())
-            .bind(points)
-            .bind(valid)
-            .execute(pool)
-            .await?;
+    #[tokio::test]
+    async fn reply_with_tiered_challenge_asks_for_the_keyboard() {
+        let (pool, dir) = (
+            forwarded_tiered_submission().await,
+            tempfile::tempdir().unwrap(),
+        );
+        let actions = DryRunBot::default();
+        let mut message = serde_json::to_value(judge_chat_message("lm", Some(99))).unwrap();
+        let mut forwarded = message.clone();
+        forwarded["message_id"] = 100.into();
+        message["reply_to_message"] = forwarded;
+        let msg = serde_json::from_value(message).unwrap();
 
-    // All of this can fail since the user might have deleted their message
-    // TODO: Handle deleted messages better, don't just ignore
-    if valid == false {
-        bot.send_message(
-            UserId(associate.parse::<u64>().unwrap()),
-            match challenge.as_str() {
-                "___unclear" => "Please resend your submission with a clear caption",
-                "___invalid" => "Your submission is invalid",
-                _ => unreachable!(),
-            },
+        judge_by_reply(
+            offline_bot(),
+            Actions::DryRun(actions.clone()),
+            msg,
+            test_config(dir.path()),
+            pool.clone(),
         )
-        .reply_parameters(ReplyParameters::new(MessageId(
-            submission_ref.parse::<i32>().unwrap(),
-        )))
-        .await?;
-        // Clear existing reactions
-        bot.set_message_reaction(
-            UserId(associate.parse::<u64>().unwrap()),
-            MessageId(submission_ref.parse::<i32>().unwrap()),
+        .await
+        .unwrap();
+
+        assert_eq!(
+            actions.recorded(),
+            [RecordedAction::SendText {
+                chat_id: JUDGE_CHAT,
+                text: "'landmark' has quality tiers, use the keyboard to pick a tier".to_owned(),
+            }]
+        );
+        assert_eq!(judgements(&pool).await, 0);
+    }
+
+    #[tokio::test]
+    async fn reaction_with_tiered_challenge_asks_for_the_keyboard() {
+        let (pool, dir) = (
+            forwarded_tiered_submission().await,
+            tempfile::tempdir().unwrap(),
+        );
+        let actions = DryRunBot::default();
+        let mut cfg = test_config(dir.path());
+        cfg.maintainers.insert(UserId(99));
+        let reaction = serde_json::from_value(serde_json::json!({
+            "chat": {"id": JUDGE_CHAT.0, "type": "supergroup", "title": "Judges"},
+            "message_id": 100,
+            "user": {"id": 99, "is_bot": false, "first_name": "Judge"},
+            "date": 1732000000,
+            "old_reaction": [],
+            "new_reaction": [{"type": "emoji", "emoji": "❤"}],
+        }))
+        .unwrap();
+
+        judge_by_reaction(
+            offline_bot(),
+            Actions::DryRun(actions.clone()),
+            cfg,
+            pool.clone(),
+            reaction,
         )
-        .erase()
-        .await?;
-    } else {
-        bot.set_message_reaction(
-            UserId(associate.parse::<u64>().unwrap()),
-            MessageId(submission_ref.parse::<i32>().unwrap()),
+        .await
+        .unwrap();
+
+        assert_eq!(
+            actions.recorded(),
+            [RecordedAction::SendText {
+                chat_id: JUDGE_CHAT,
+                text: "'landmark' has quality tiers, use the keyboard to pick a tier".to_owned(),
+            }]
+        );
+        assert_eq!(judgements(&pool).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_submission_completes_nothing() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO challenges (name, short_name, points, prerequisite)
+            VALUES ('landmark', 'lm', 3, NULL), ('tower', 'tw', 3, 'landmark')",
         )
-        .reaction(vec![ReactionType::Emoji {
-            emoji: "❤".to_owned(),
-        }])
-        .await?;
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO submissions (message_id, user, team, date, caption, type, test)
+            VALUES (-1, 99, 'Rocket', '2024-11-20 10:00:00', '', 0, 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO judgement (submission_id, challenge_name, points, valid)
+            VALUES (-1, 'landmark', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let (open, locked) = team_open_and_locked_challenges(&pool, "Rocket")
+            .await
+            .unwrap();
+
+        let names = |challenges: &[Challenge]| {
+            challenges
+                .iter()
+                .map(|challenge| challenge.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&open), ["landmark"]);
+        assert_eq!(names(&locked), ["tower"]);
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn unforwarded_submissions_are_queued_again() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO submissions
+                (message_id, user, team, date, caption, type, excluded, test, forwarded_message_id)
+            VALUES (7, 42, 'Rocket', '2024-11-20 10:00:02', '', 0, 0, 0, NULL),
+                (8, 42, 'Rocket', '2024-11-20 10:00:01', '', 0, 0, 0, NULL),
+                (9, 42, 'Rocket', '2024-11-20 10:00:00', '', 0, 0, 0, 100),
+                (10, 42, 'Rocket', '2024-11-20 10:00:00', '', 0, 1, 0, NULL),
+                (-1, 42, 'Rocket', '2024-11-20 10:00:00', '', 0, 0, 1, NULL),
+                (11, 42, 'Rocket', '2024-11-20 10:00:00', '', 0, 0, 0, NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO judgement (submission_id, challenge_name, points, valid)
+            VALUES (11, 'landmark', 1, 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let (queue, mut queued) = tokio::sync::mpsc::unbounded_channel();
+
+        let count = JudgeQueue(queue).requeue_unforwarded(&pool).await.unwrap();
+
+        assert_eq!(count, 2);
+        let mut requeued = vec![];
+        while let Ok(submission) = queued.try_recv() {
+            assert_eq!(submission.chat_id, ChatId(PARTICIPANT));
+            assert!(submission.new);
+            requeued.push(submission.message_id.0);
+        }
+        assert_eq!(requeued, [8, 7]);
+    }
+
+    #[tokio::test]
+    async fn manually_closed_topic_stays_closed() {
+        let pool = test_pool().await;
+        sqlx::query(
+            "INSERT INTO users (id, first_name, team) VALUES (1, 'Ada', 'Rocket'), (2, 'Bo', 'Magma');
+            INSERT INTO forums (id, name, open, title, closed_manually)
+            VALUES (10, 'Rocket', 0, 'Rocket (0)', 1), (11, 'Magma', 0, 'Magma (0)', 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let actions = DryRunBot::default();
+
+        update_teams_in_forum(
+            &actions,
+            &pool,
+            &Mutex::new(()),
+            &Recipient::Id(ChatId(-200)),
+        )
+        .await
+        .unwrap();
+
+        // Only the topic closed by the reconciliation itself comes back
+        assert_eq!(
+            actions.recorded(),
+            [RecordedAction::ReopenTopic {
+                thread_id: ThreadId(MessageId(11)),
+            }]
+        );
+    }
+
+    /// Reply of `report_unprocessable_file` to `PARTICIPANT`
+    fn unprocessable_file_reply() -> RecordedAction {
+        RecordedAction::SendText {
+            chat_id: ChatId(PARTICIPANT),
+            text: "We couldn't process that file, please try a smaller one or try again".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_download_is_reported() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let cfg = test_config(dir.path());
+        join(&pool, "Rocket").await;
+        let actions = DryRunBot::default();
+        actions.fail(FailingCall::Download);
+
+        let (result, _queued) = submit(&actions, &pool, &cfg, photo_message(7)).await;
+
+        result.unwrap();
+        assert_eq!(actions.recorded().last(), Some(&unprocessable_file_reply()));
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM submissions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 0);
+    }
+
+    #[tokio::test]
+    async fn failed_forward_removes_download() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let cfg = test_config(dir.path());
+        join(&pool, "Rocket").await;
+        let actions = DryRunBot::default();
+        let (result, mut queued) = submit(&actions, &pool, &cfg, photo_message(7)).await;
+        result.unwrap();
+        let file_path: String = sqlx::query_scalar("SELECT file_path FROM submissions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(Path::new(&file_path).exists());
+
+        actions.fail(FailingCall::Forward);
+        let submission = queued.try_recv().unwrap();
+        deliver_to_judges(&Actions::DryRun(actions.clone()), &cfg, &pool, submission)
+            .await
+            .unwrap();
+
+        assert!(!Path::new(&file_path).exists());
+        assert_eq!(actions.recorded().last(), Some(&unprocessable_file_reply()));
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM submissions")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 0);
+    }
+
+    #[tokio::test]
+    async fn submission_shows_upload_before_download() {
+        let (pool, dir) = (test_pool().await, tempfile::tempdir().unwrap());
+        let cfg = test_config(dir.path());
+        join(&pool, "Rocket").await;
+        let actions = DryRunBot::default();
+
+        let (result, _queued) = submit(&actions, &pool, &cfg, photo_message(7)).await;
+
+        result.unwrap();
+        let recorded = actions.recorded();
+        assert_eq!(
+            recorded[..2],
+            [
+                RecordedAction::ChatAction {
+                    chat_id: ChatId(PARTICIPANT),
+                    action: teloxide::types::ChatAction::UploadPhoto,
+                },
+                RecordedAction::Download {
+                    file_id: "photo-file".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_team_name_is_invalid() {
+        assert!(invalid_team_name("").is_some());
+        assert!(invalid_team_name("   ").is_some());
+    }
+
+    #[test]
+    fn command_is_no_team_name() {
+        assert!(invalid_team_name("/join_team").is_some());
+    }
+
+    #[test]
+    fn team_name_length_counts_characters() {
+        assert_eq!(invalid_team_name(&"ü".repeat(MAX_TEAM_NAME_LENGTH)), None);
+        assert!(invalid_team_name(&"ü".repeat(MAX_TEAM_NAME_LENGTH + 1)).is_some());
+    }
+
+    #[test]
+    fn reserved_team_names_ignore_case() {
+        assert!(invalid_team_name("Help").is_some());
+        assert!(invalid_team_name(" JOIN_TEAM ").is_some());
+        assert!(invalid_team_name("__Self_Test__").is_some());
+    }
+
+    #[test]
+    fn valid_team_name() {
+        assert_eq!(invalid_team_name("Team Rocket 🚀"), None);
+    }
+
+    #[test]
+    fn only_judged_defaults_to_false() {
+        assert_eq!(parse_cleanup_files("7".to_owned()).unwrap(), (7, false));
+        assert_eq!(
+            parse_cleanup_files(" 7 true ".to_owned()).unwrap(),
+            (7, true)
+        );
+        assert!(parse_cleanup_files("".to_owned()).is_err());
+        assert!(parse_cleanup_files("7 yes".to_owned()).is_err());
+        assert!(parse_cleanup_files("7 true 3".to_owned()).is_err());
+    }
+
+    #[test]
+    fn long_caption_ends_in_ellipsis() {
+        assert_eq!(truncate_caption("Döner", 5), ("Döner".to_owned(), false));
+        assert_eq!(
+            truncate_caption("Döner macht", 5),
+            ("Döne…".to_owned(), true)
+        );
+    }
+
+    #[test]
+    fn judges_see_truncation_note() {
+        assert_eq!(submission_note(None, false), None);
+        assert_eq!(
+            submission_note(None, true).as_deref(),
+            Some("✂️ caption truncated, see the forwarded message")
+        );
+        let taken_at = scoring::parse_date("2024-11-19 12:00:00");
+        assert_eq!(
+            submission_note(taken_at, true).as_deref(),
+            Some("⚠️ taken before event (2024-11-19 12:00:00)\n✂️ caption truncated, see the forwarded message")
+        );
+    }
+
+    #[test]
+    fn file_name_part_strips_path_separators() {
+        assert_eq!(file_name_part("Team/Rocket"), "Team_Rocket");
+        assert_eq!(file_name_part("../../etc/passwd"), "etc_passwd");
+        assert_eq!(file_name_part(".."), "unknown");
+    }
+
+    #[test]
+    fn file_name_part_replaces_emoji() {
+        assert_eq!(file_name_part("🚀 Rocket 🚀"), "Rocket");
+        assert_eq!(file_name_part("Team 🚀 Rocket"), "Team___Rocket");
+    }
+
+    #[test]
+    fn empty_file_name_part_is_unknown() {
+        assert_eq!(file_name_part(""), "unknown");
+        assert_eq!(file_name_part("🦄"), "unknown");
+    }
+
+    #[test]
+    fn file_name_part_is_truncated() {
+        let part = file_name_part(&"a".repeat(MAX_FILE_NAME_PART_LENGTH + 10));
+        assert_eq!(part, "a".repeat(MAX_FILE_NAME_PART_LENGTH));
+    }
 }
@@ -0,0 +1,24 @@
+//! Metadata embedded in submitted media
+use chrono::{NaiveDate, NaiveDateTime};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Capture time from the EXIF `DateTimeOriginal` tag of a photo.
+///
+/// The time is the camera's local time. Telegram strips EXIF from compressed photos, so
+/// this is mostly `None` unless the photo was sent as a file.
+pub fn capture_time(path: &Path) -> Option<NaiveDateTime> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let time = exif::DateTime::from_ascii(values.first()?).ok()?;
+    NaiveDate::from_ymd_opt(time.year.into(), time.month.into(), time.day.into())?.and_hms_opt(
+        time.hour.into(),
+        time.minute.into(),
+        time.second.into(),
+    )
+}
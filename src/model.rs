@@ -1,3 +1,6 @@
+use std::fmt;
+
+#[allow(dead_code)]
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Submission {
     pub message_id: i64,
@@ -8,6 +11,7 @@ pub struct Submission {
     pub r#type: i32,
 }
 
+#[allow(dead_code)]
 #[derive(sqlx::FromRow, Debug, Clone)]
 pub struct SubmissionExtended {
     pub message_id: i64,
@@ -26,9 +30,9 @@ pub struct Team {
     pub team: String,
     pub count: i64,
 }
-impl ToString for Team {
-    fn to_string(&self) -> String {
-        self.team.clone()
+impl fmt::Display for Team {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.team)
     }
 }
 
@@ -36,12 +40,73 @@ impl ToString for Team {
 pub struct Forum {
     pub id: i32,
     pub name: String,
+    pub open: bool,
+    /// Topic title last shown: the team name with its emoji and submission count; `None` for
+    /// topics named after the team
+    #[sqlx(default)]
+    pub title: Option<String>,
+    /// Closed with /close_forum, stays closed until /reopen_forum
+    #[sqlx(default)]
+    pub closed_manually: bool,
 }
 
-#[derive(sqlx::FromRow, Debug, Clone)]
+#[derive(sqlx::FromRow, Debug, Clone, Default)]
 pub struct Challenge {
+    /// SQLite `rowid`, used to reference the challenge in callback data
+    #[sqlx(default)]
+    pub id: i64,
     pub name: String,
     pub short_name: String,
+    #[sqlx(default)]
+    pub points: Option<i32>,
+    #[sqlx(default)]
+    pub starts_at: Option<String>,
+    #[sqlx(default)]
+    pub decay_half_life: Option<f64>,
+    #[sqlx(default)]
+    pub decay_floor: Option<i32>,
+    #[sqlx(default)]
+    pub emoji: Option<String>,
+    #[sqlx(default)]
+    pub desc: Option<String>,
+    /// Comma separated points a judge can pick from, e.g. `1,2,3`
+    #[sqlx(default)]
+    pub tiers: Option<String>,
+    /// Challenge that has to be completed before this one is offered
+    #[sqlx(default)]
+    pub prerequisite: Option<String>,
+    /// Comma separated bonus for the first, second, ... team to complete it, e.g. `5,3,1`
+    #[sqlx(default)]
+    pub podium_bonus: Option<String>,
+}
+impl Challenge {
+    /// Points of each quality tier, empty if the challenge has fixed points
+    pub fn tier_points(&self) -> Vec<i32> {
+        self.tiers
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|x| x.trim().parse().ok())
+            .collect()
+    }
+
+    /// Bonus of each podium place, empty if the order of completion doesn't matter
+    pub fn podium_bonuses(&self) -> Vec<i32> {
+        self.podium_bonus
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|x| x.trim().parse().ok())
+            .collect()
+    }
+
+    /// Short name prefixed with the challenge's emoji, if any
+    pub fn label(&self) -> String {
+        match &self.emoji {
+            Some(emoji) if !emoji.is_empty() => format!("{} {}", emoji, self.short_name),
+            _ => self.short_name.clone(),
+        }
+    }
 }
 
 #[derive(sqlx::FromRow, Debug)]
@@ -52,8 +117,8 @@ pub struct User {
     pub first_name: String,
     pub last_name: Option<String>,
 }
-impl ToString for User {
-    fn to_string(&self) -> String {
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let name = if let Some(last_name) = &self.last_name {
             format!("{} {}", self.first_name, last_name)
         } else {
@@ -61,13 +126,14 @@ impl ToString for User {
         };
 
         if let Some(username) = &self.username {
-            format!("{} @{}", &name, &username)
+            write!(f, "{} @{}", &name, &username)
         } else {
-            name
+            write!(f, "{}", name)
         }
     }
 }
 
+#[allow(dead_code)]
 #[derive(sqlx::FromRow, Debug)]
 pub struct Config {
     pub name: String,
@@ -87,3 +153,26 @@ pub struct TeamScore {
     pub team: String,
     pub score: i64,
 }
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct TeamChange {
+    pub user_id: i64,
+    pub first_name: Option<String>,
+    pub old_team: String,
+    pub new_team: String,
+    pub changed_at: String,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct MediaCount {
+    pub team: String,
+    pub photos: i64,
+    pub videos: i64,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Adjustment {
+    pub points: i32,
+    pub reason: String,
+    pub created_at: String,
+}
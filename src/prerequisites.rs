@@ -0,0 +1,47 @@
+//! Progressive unlocking of challenges through their `prerequisite`
+use crate::model::Challenge;
+use std::collections::{HashMap, HashSet};
+
+/// Names of the challenges whose prerequisites form a cycle. Their prerequisites are
+/// ignored, otherwise none of them could ever be unlocked.
+pub fn cyclic(challenges: &[Challenge]) -> HashSet<String> {
+    let prerequisites: HashMap<&str, &str> = challenges
+        .iter()
+        .filter_map(|challenge| Some((challenge.name.as_str(), challenge.prerequisite.as_deref()?)))
+        .collect();
+    let mut cyclic = HashSet::new();
+    for start in prerequisites.keys() {
+        // Follow the chain of prerequisites, it is a cycle if it leads back to the start
+        let mut visited = HashSet::new();
+        let mut current = *start;
+        while let Some(next) = prerequisites.get(current) {
+            if *next == *start {
+                cyclic.insert(start.to_string());
+                break;
+            }
+            if !visited.insert(*next) {
+                break;
+            }
+            current = next;
+        }
+    }
+    cyclic
+}
+
+/// Whether `challenge` is offered to a team that completed the challenges in `completed`.
+/// Prerequisites that are unknown or part of a cycle don't lock a challenge.
+pub fn is_unlocked(
+    challenge: &Challenge,
+    completed: &HashSet<String>,
+    known: &HashSet<&str>,
+    cyclic: &HashSet<String>,
+) -> bool {
+    match challenge.prerequisite.as_deref() {
+        None | Some("") => true,
+        Some(prerequisite) => {
+            completed.contains(prerequisite)
+                || !known.contains(prerequisite)
+                || cyclic.contains(&challenge.name)
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::NaiveDateTime;
+
+use crate::model::Challenge;
+
+/// Points awarded for a challenge without an explicit `points` value
+const DEFAULT_POINTS: i32 = 1;
+
+/// Parse a date as stored by SQLite's `datetime()`, with or without the `T` separator
+pub fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+}
+
+/// Points for a valid submission of `challenge` received at `submission_date`.
+///
+/// Without decay the challenge's base points are awarded. If the challenge has both a
+/// `starts_at` and a `decay_half_life` (in hours), the points halve every half-life:
+///
+/// `points = max(decay_floor, round(base * 0.5 ^ (hours_since_start / decay_half_life)))`
+///
+/// Submissions received before the start get the full base points; `decay_floor`
+/// defaults to 0.
+pub fn points_for(challenge: &Challenge, submission_date: &str) -> i32 {
+    let base = challenge.points.unwrap_or(DEFAULT_POINTS);
+    let (Some(starts_at), Some(half_life)) = (&challenge.starts_at, challenge.decay_half_life)
+    else {
+        return base;
+    };
+    if half_life <= 0.0 {
+        log::warn!(
+            "Ignoring non-positive decay half-life for {:?}",
+            challenge.name
+        );
+        return base;
+    }
+    let (Some(start), Some(submitted)) = (parse_date(starts_at), parse_date(submission_date))
+    else {
+        log::warn!(
+            "Could not parse dates {:?}/{:?} for decay of {:?}",
+            starts_at,
+            submission_date,
+            challenge.name
+        );
+        return base;
+    };
+
+    let elapsed_hours = (submitted - start).num_seconds().max(0) as f64 / 3600.0;
+    let decayed = (base as f64 * 0.5f64.powf(elapsed_hours / half_life)).round() as i32;
+    decayed.max(challenge.decay_floor.unwrap_or(0))
+}
+
+/// Scale `points` by a configured multiplier such as `"1.5"`, rounding to whole points.
+///
+/// Unparsable or negative multipliers leave the points unchanged.
+pub fn apply_multiplier(points: i32, multiplier: &str) -> i32 {
+    match multiplier.trim().parse::<f64>() {
+        Ok(multiplier) if multiplier >= 0.0 => (points as f64 * multiplier).round() as i32,
+        _ => {
+            log::warn!("Ignoring invalid points multiplier {:?}", multiplier);
+            points
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decaying(floor: Option<i32>) -> Challenge {
+        Challenge {
+            name: "landmark".to_owned(),
+            points: Some(8),
+            starts_at: Some("2024-11-20 10:00:00".to_owned()),
+            decay_half_life: Some(2.0),
+            decay_floor: floor,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn full_points_before_start() {
+        assert_eq!(points_for(&decaying(None), "2024-11-20 09:00:00"), 8);
+    }
+
+    #[test]
+    fn half_points_after_one_half_life() {
+        assert_eq!(points_for(&decaying(None), "2024-11-20 12:00:00"), 4);
+        assert_eq!(points_for(&decaying(None), "2024-11-20T12:00:00"), 4);
+    }
+
+    #[test]
+    fn decay_stops_at_floor() {
+        assert_eq!(points_for(&decaying(Some(3)), "2024-11-21 10:00:00"), 3);
+        assert_eq!(points_for(&decaying(None), "2024-11-21 10:00:00"), 0);
+    }
+
+    #[test]
+    fn base_points_without_decay() {
+        let challenge = Challenge {
+            decay_half_life: None,
+            ..decaying(None)
+        };
+        assert_eq!(points_for(&challenge, "2024-11-21 10:00:00"), 8);
+        assert_eq!(points_for(&Challenge::default(), "2024-11-21 10:00:00"), 1);
+    }
+}
@@ -0,0 +1,68 @@
+//! Compressed previews of submitted photos
+use image::{codecs::jpeg::JpegEncoder, ImageResult};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// Longest side of a thumbnail in pixels
+const THUMBNAIL_SIZE: u32 = 640;
+const THUMBNAIL_QUALITY: u8 = 70;
+/// Blurhashes only keep a few colors, so they are computed from a tiny version of the photo
+const BLURHASH_SOURCE_SIZE: u32 = 32;
+/// Horizontal and vertical components of a blurhash, more give more detail
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// Write a downscaled JPEG next to the photo at `path` and return its path.
+///
+/// `photo.jpg` gets the thumbnail `photo_thumb.jpg`. Photos smaller than the thumbnail size
+/// are only recompressed.
+pub fn create_thumbnail(path: &Path) -> ImageResult<PathBuf> {
+    let image = image::open(path)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let destination = path.with_file_name(format!("{}_thumb.jpg", stem));
+    let file = BufWriter::new(File::create(&destination)?);
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(file, THUMBNAIL_QUALITY))?;
+    Ok(destination)
+}
+
+/// Blurhash of the photo at `path`, a short string that decodes to a blurred placeholder
+pub fn blurhash(path: &Path) -> ImageResult<String> {
+    let image = image::open(path)?
+        .thumbnail(BLURHASH_SOURCE_SIZE, BLURHASH_SOURCE_SIZE)
+        .to_rgba8();
+    let (x, y) = BLURHASH_COMPONENTS;
+    Ok(
+        blurhash::encode(x, y, image.width(), image.height(), image.as_raw())
+            .expect("blurhash components are in range"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+
+    #[test]
+    fn thumbnail_fits_the_bound() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("photo.jpg");
+        RgbImage::from_pixel(
+            THUMBNAIL_SIZE * 2,
+            THUMBNAIL_SIZE,
+            image::Rgb([200, 30, 30]),
+        )
+        .save_with_format(&photo, ImageFormat::Jpeg)
+        .unwrap();
+
+        let thumbnail = create_thumbnail(&photo).unwrap();
+
+        assert_eq!(thumbnail, dir.path().join("photo_thumb.jpg"));
+        let (width, height) = image::image_dimensions(&thumbnail).unwrap();
+        assert_eq!(width, THUMBNAIL_SIZE);
+        assert!(height <= THUMBNAIL_SIZE);
+    }
+}